@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use crate::error::GameError;
+
+/// Decimal places of every token: one whole DTOSHI is `UNITS_PER_TOKEN`
+/// MTOSHI, the unit balances are kept in.
+pub const DECIMALS: u32 = 9;
+pub const UNITS_PER_TOKEN: u64 = 10u64.pow(DECIMALS);
+
+/// A non-negative token amount in fixed point with `DECIMALS` places, held as
+/// a whole number of base units. All arithmetic is checked; overflow and
+/// going below zero are errors rather than wrapping or panicking, and
+/// fractions are taken with integer ratios instead of floats. Serializes as
+/// the bare number of units.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Amount(u64);
+
+impl Amount {
+    pub const ZERO: Amount = Amount(0);
+
+    pub const fn from_units(units: u64) -> Self {
+        Amount(units)
+    }
+
+    pub const fn units(self) -> u64 {
+        self.0
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    /// `tokens` whole tokens.
+    pub fn from_tokens(tokens: u64) -> Result<Self, GameError> {
+        tokens.checked_mul(UNITS_PER_TOKEN).map(Amount).ok_or_else(|| overflow(format!("{} tokens", tokens)))
+    }
+
+    pub fn checked_add(self, other: Amount) -> Result<Self, GameError> {
+        self.0.checked_add(other.0).map(Amount).ok_or_else(|| overflow(format!("{} + {}", self, other)))
+    }
+
+    pub fn checked_sub(self, other: Amount) -> Result<Self, GameError> {
+        self.0.checked_sub(other.0).map(Amount).ok_or_else(|| overflow(format!("{} - {}", self, other)))
+    }
+
+    /// `self * numerator / denominator`, rounded down. The product is taken
+    /// in 128 bits, so only a result that does not fit is an overflow.
+    pub fn mul_div(self, numerator: u64, denominator: u64) -> Result<Self, GameError> {
+        let operation = || format!("{} * {} / {}", self, numerator, denominator);
+        if denominator == 0 {
+            return Err(overflow(operation()));
+        }
+        let result = self.0 as u128 * numerator as u128 / denominator as u128;
+        u64::try_from(result).map(Amount).map_err(|_| overflow(operation()))
+    }
+
+    /// `percent`% of the amount, rounded down.
+    pub fn percent(self, percent: u64) -> Result<Self, GameError> {
+        self.mul_div(percent, 100)
+    }
+
+    pub fn checked_sum(amounts: impl IntoIterator<Item = Amount>) -> Result<Self, GameError> {
+        amounts.into_iter().try_fold(Amount::ZERO, Amount::checked_add)
+    }
+}
+
+impl fmt::Display for Amount {
+    /// Whole tokens with all `DECIMALS` places, e.g. `1.050000000`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{:0width$}", self.0 / UNITS_PER_TOKEN, self.0 % UNITS_PER_TOKEN, width = DECIMALS as usize)
+    }
+}
+
+fn overflow(operation: String) -> GameError {
+    GameError::Overflow { operation }
+}
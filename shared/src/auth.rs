@@ -0,0 +1,160 @@
+use libp2p::identity::{Keypair, PublicKey};
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use crate::blockchain::Action;
+use crate::runtime::catena_node::sha256_hex;
+
+/// Account id for a public key: its base58 peer ID, the same `peer_id` the
+/// `utils` `generate-keys` binary writes next to the protobuf-encoded private key.
+pub fn account_id(public_key: &PublicKey) -> String {
+    PeerId::from_public_key(public_key).to_string()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AuthError {
+    Unsigned,
+    InvalidPublicKey,
+    InvalidSignature,
+    SignerMismatch { signer: String, player: String },
+    MissingCosignature { player: String },
+    InvalidNonce { expected: u64, got: u64 },
+    InsufficientFee { needed: u64, available: u64 },
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthError::Unsigned => write!(f, "Action must be signed"),
+            AuthError::InvalidPublicKey => write!(f, "Invalid public key"),
+            AuthError::InvalidSignature => write!(f, "Invalid signature"),
+            AuthError::SignerMismatch { signer, player } => write!(f, "Signer {} cannot act as player {}", signer, player),
+            AuthError::MissingCosignature { player } => write!(f, "Action also needs the signature of player {}", player),
+            AuthError::InvalidNonce { expected, got } => write!(f, "Invalid nonce: expected {}, got {}", expected, got),
+            AuthError::InsufficientFee { needed, available } => write!(f, "Fee of {} exceeds MTOSHI balance of {}", needed, available),
+        }
+    }
+}
+
+impl std::error::Error for AuthError {}
+
+/// Second party's signature over the same bytes as the sender's, for
+/// actions such as battles and trades that involve another player.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cosignature {
+    /// Hex of the cosigner's protobuf-encoded ed25519 public key.
+    pub public_key: String,
+    pub signature: String,
+}
+
+/// Envelope that proves who sent an `Action`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedAction {
+    pub action: Action,
+    /// Hex of the sender's protobuf-encoded ed25519 public key.
+    pub public_key: String,
+    /// Hex signature over `SignedAction::signing_bytes(&action, nonce, tip)`.
+    pub signature: String,
+    pub nonce: u64,
+    /// Optional tip paid on top of the action's base fee.
+    #[serde(default)]
+    pub tip: u64,
+    /// Present exactly when the action has a `cosigner`. The sender's nonce
+    /// alone protects the envelope against replay.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cosignature: Option<Cosignature>,
+}
+
+impl SignedAction {
+    pub fn signing_bytes(action: &Action, nonce: u64, tip: u64) -> Vec<u8> {
+        serde_json::to_vec(&(action, nonce, tip)).expect("action serializes")
+    }
+
+    pub fn sign(keypair: &Keypair, action: Action, nonce: u64) -> Self {
+        Self::sign_with_tip(keypair, action, nonce, 0)
+    }
+
+    pub fn sign_with_tip(keypair: &Keypair, action: Action, nonce: u64, tip: u64) -> Self {
+        let signature = keypair
+            .sign(&Self::signing_bytes(&action, nonce, tip))
+            .expect("ed25519 signing does not fail");
+        SignedAction {
+            action,
+            public_key: hex::encode(keypair.public().encode_protobuf()),
+            signature: hex::encode(signature),
+            nonce,
+            tip,
+            cosignature: None,
+        }
+    }
+
+    /// Adds the second party's signature. The sender signs first and hands
+    /// the envelope to the cosigner, who accepts the offer by cosigning it.
+    pub fn cosign(mut self, keypair: &Keypair) -> Self {
+        let signature = keypair
+            .sign(&Self::signing_bytes(&self.action, self.nonce, self.tip))
+            .expect("ed25519 signing does not fail");
+        self.cosignature = Some(Cosignature {
+            public_key: hex::encode(keypair.public().encode_protobuf()),
+            signature: hex::encode(signature),
+        });
+        self
+    }
+
+    /// Total fee charged for this action: its base fee plus the tip.
+    pub fn fee(&self) -> u64 {
+        self.action.base_fee().saturating_add(self.tip)
+    }
+
+    /// Identifies the envelope in the mempool and in blocks.
+    pub fn hash(&self) -> String {
+        sha256_hex(&self.leaf_bytes())
+    }
+
+    /// Bytes committed to as this action's leaf in the block's `actions_root`.
+    pub fn leaf_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("signed action serializes")
+    }
+
+    /// Checks the signature and that the signer is the player the action
+    /// names as acting, and for actions with a second party that it cosigned
+    /// them. Returns the signer's account id.
+    pub fn verify(&self) -> Result<String, AuthError> {
+        if self.public_key.is_empty() || self.signature.is_empty() {
+            return Err(AuthError::Unsigned);
+        }
+        let signing_bytes = Self::signing_bytes(&self.action, self.nonce, self.tip);
+        let signer = verify_signature(&self.public_key, &self.signature, &signing_bytes)?;
+        if let Some(player) = self.action.actor() {
+            if player != signer {
+                return Err(AuthError::SignerMismatch { signer, player: player.to_string() });
+            }
+        }
+        match (self.action.cosigner(), &self.cosignature) {
+            (Some(player), None) => return Err(AuthError::MissingCosignature { player: player.to_string() }),
+            (Some(player), Some(cosignature)) => {
+                let cosigner = verify_signature(&cosignature.public_key, &cosignature.signature, &signing_bytes)?;
+                if cosigner != player {
+                    return Err(AuthError::SignerMismatch { signer: cosigner, player: player.to_string() });
+                }
+            }
+            (None, Some(_)) => return Err(AuthError::InvalidSignature),
+            (None, None) => {}
+        }
+        Ok(signer)
+    }
+}
+
+/// Checks a hex signature over `message` by a hex protobuf-encoded key,
+/// returning the key's account id.
+fn verify_signature(public_key: &str, signature: &str, message: &[u8]) -> Result<String, AuthError> {
+    let public_key = hex::decode(public_key)
+        .ok()
+        .and_then(|bytes| PublicKey::try_decode_protobuf(&bytes).ok())
+        .ok_or(AuthError::InvalidPublicKey)?;
+    let signature = hex::decode(signature).map_err(|_| AuthError::InvalidSignature)?;
+    if !public_key.verify(message, &signature) {
+        return Err(AuthError::InvalidSignature);
+    }
+    Ok(account_id(&public_key))
+}
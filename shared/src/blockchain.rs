@@ -1,21 +1,75 @@
+use libp2p::identity::Keypair;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use crate::runtime::{GameFiRuntime as FullGameFiRuntime, catena_node::Block};
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::amount::{Amount, UNITS_PER_TOKEN};
+use crate::auth::{account_id, AuthError, SignedAction};
+use crate::chain::{Chain, MAX_REORG_DEPTH};
+use crate::consensus::Consensus;
+use crate::error::GameError;
+use crate::mempool::{AccountState, Mempool, MempoolError, PendingEntry, MAX_BLOCK_ACTIONS};
+use crate::merkle::MerkleProof;
+use crate::ledger::{self, Ledger, MTOSHI, SOLUTIO};
+use crate::runtime::{GameFiRuntime, catena_node::{Block, Transaction}, DEFAULT_DIFFICULTY};
+use crate::staking::{DOUBLE_SIGN_SLASH_PERCENT, JAIL_MISSED_SLOTS};
+use crate::state::{self, AccountProof, CommittedState, StateLeaf};
+use crate::storage::Storage;
+
+/// Base fee, in MTOSHI base units, for actions that touch every player.
+pub const BASE_FEE_HEAVY: u64 = 10_000_000; // 0.01 DTOSHI
+/// Base fee for every other state-changing action.
+pub const BASE_FEE_STANDARD: u64 = 1_000_000; // 0.001 DTOSHI
+/// Price of a paid like, split 80/10/10 between creator and pools.
+const PAID_LIKE_COST: Amount = Amount::from_units(50_000_000); // 0.05 DTOSHI
+/// Amount of a token the faucet hands out per request, enough for a few
+/// hundred standard fees.
+pub const FAUCET_AMOUNT: Amount = Amount::from_units(10 * UNITS_PER_TOKEN);
+/// Blocks a player has to wait between faucet requests for the same token.
+pub const FAUCET_COOLDOWN_BLOCKS: u64 = 100;
+/// Blocks after which the outcome of an included action can no longer be looked up.
+pub const RECEIPT_RETENTION_BLOCKS: u64 = 10_000;
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Action {
     RequestTokens { player: String, token: String },
     GetBalance { player: String, token: String },
+    GetTokens,
     GetPlayer { player: String },
     GetContentStats { player: String },
     SimulateActivity,
-    MineBlock { miner: String, index: u64 },
+    MineBlock { miner: String },
+    GetDifficulty,
+    GetTransactionProof { height: u64, tx_index: u32 },
+    GetStateRoot { height: u64 },
+    /// Proof against the state root of the block at `height`, the head if
+    /// `None`. Blocks older than `MAX_REORG_DEPTH` cannot be proven against.
+    GetAccountProof {
+        player: String,
+        #[serde(default)]
+        height: Option<u64>,
+    },
+    GetFinalizedHead,
+    GetReceipt { hash: String },
     RecordActivity { player: String, activity_type: u32 },
     Battle { player1: String, player2: String },
     TradeItems { player1: String, player1_item_index: u32, player2: String, player2_item_index: u32 },
     DistributeMtoshiRewards,
     ShowLeaderboard,
     GetMtoshiBalance { player: String },
+    GetNonce { player: String },
+    Stake { player: String, amount: u64 },
+    Unstake { player: String, amount: u64 },
+    ClaimRewards { player: String },
+    GetStake { player: String },
+    ReportDoubleSign { first: Box<Block>, second: Box<Block> },
+    Unjail { validator: String },
+    GetPools,
     MintNft { player: String, name: String, value: u64 },
     BuyNft { buyer: String, seller: String, nft_index: u32 },
     SellNft { seller: String, nft_index: u32, price: u64 },
@@ -30,23 +84,125 @@ pub enum Action {
     GetPosts,
     GetVideos,
     GetNftListings,
+    PendingTransactions,
+    DropTransaction { hash: String },
+}
+
+impl Action {
+    /// The player an action is performed as, which must match the signer.
+    /// Battles and trades are initiated by `player1` and cosigned by `player2`. `None` for actions with
+    /// no acting player and for read-only queries.
+    pub fn actor(&self) -> Option<&str> {
+        match self {
+            Action::RequestTokens { player, .. }
+            | Action::RecordActivity { player, .. }
+            | Action::MintNft { player, .. }
+            | Action::UploadContent { player, .. }
+            | Action::LikeContent { player, .. }
+            | Action::CommentContent { player, .. }
+            | Action::ViewContent { player, .. }
+            | Action::PostContent { player, .. }
+            | Action::LikePost { player, .. }
+            | Action::CommentPost { player, .. }
+            | Action::BoostPost { player, .. }
+            | Action::Stake { player, .. }
+            | Action::Unstake { player, .. }
+            | Action::ClaimRewards { player }
+            | Action::Unjail { validator: player } => Some(player),
+            Action::MineBlock { miner } => Some(miner),
+            Action::Battle { player1, .. } | Action::TradeItems { player1, .. } => Some(player1),
+            Action::BuyNft { buyer, .. } => Some(buyer),
+            Action::SellNft { seller, .. } => Some(seller),
+            _ => None,
+        }
+    }
+
+    /// The other player an action involves, who must cosign it: the
+    /// opponent in a battle and the counterparty of a trade.
+    pub fn cosigner(&self) -> Option<&str> {
+        match self {
+            Action::Battle { player2, .. } | Action::TradeItems { player2, .. } => Some(player2),
+            _ => None,
+        }
+    }
+
+    /// Fee charged before the action runs, on top of any tip. The faucet,
+    /// queries and node-local actions are free; the faucet is rate limited
+    /// by `FAUCET_COOLDOWN_BLOCKS` instead. `MineBlock` is free too, so a
+    /// miner without funds can produce the first block on a fresh chain.
+    pub fn base_fee(&self) -> u64 {
+        match self {
+            Action::SimulateActivity | Action::DistributeMtoshiRewards => BASE_FEE_HEAVY,
+            Action::RequestTokens { .. } | Action::MineBlock { .. } => 0,
+            action if action.is_query() || action.is_local() => 0,
+            _ => BASE_FEE_STANDARD,
+        }
+    }
+
+    /// Read-only actions that may be processed without a signature.
+    pub fn is_query(&self) -> bool {
+        matches!(
+            self,
+            Action::GetBalance { .. }
+                | Action::GetTokens
+                | Action::GetPlayer { .. }
+                | Action::GetContentStats { .. }
+                | Action::GetDifficulty
+                | Action::GetTransactionProof { .. }
+                | Action::GetStateRoot { .. }
+                | Action::GetAccountProof { .. }
+                | Action::GetFinalizedHead
+                | Action::GetReceipt { .. }
+                | Action::ShowLeaderboard
+                | Action::GetMtoshiBalance { .. }
+                | Action::GetNonce { .. }
+                | Action::GetStake { .. }
+                | Action::GetPools
+                | Action::GetPosts
+                | Action::GetVideos
+                | Action::GetNftListings
+                | Action::PendingTransactions
+        )
+    }
+
+    /// Node-local actions that change this node's view but never enter a
+    /// block. Only the node's operator may send them, signed.
+    pub fn is_local(&self) -> bool {
+        matches!(self, Action::DropTransaction { .. })
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ActionResult {
     TokensRequested { player: String, token: String, amount: u64 },
     Balance { player: String, token: String, balance: u64 },
+    Tokens { tokens: Vec<(String, u32)> }, // (id, decimals)
     Player { player: String, data: HashMap<String, String> },
     ContentStats { player: String, stats: HashMap<String, u64> },
-    Error { message: String },
+    Error { error: GameError },
     ActivitySimulated,
     BlockMined { miner: String, block: Option<Block> },
+    Difficulty { height: u64, difficulty: u64 },
+    TransactionProof { height: u64, tx_root: String, transaction: Transaction, proof: MerkleProof },
+    StateRoot { height: u64, state_root: String },
+    AccountProof { proof: AccountProof },
+    FinalizedHead { height: u64, hash: String },
+    ActionQueued { hash: String },
+    Receipt { hash: String, height: u64, block_hash: String, result: Box<ActionResult> },
     ActivityRecorded { player: String, activity_type: u32, points: u64 },
     BattleResult { winner: String, loser: String },
     ItemsTraded { player1: String, player2: String, success: bool },
     MtoshiDistributed { total_reward: u64 },
     Leaderboard { players: Vec<(String, u32, u64, u64, u64)> },
     MtoshiBalance { player: String, balance: u64 },
+    Nonce { player: String, nonce: u64 },
+    Staked { player: String, amount: u64, stake: u64 },
+    Unstaked { player: String, amount: u64, release_height: u64 },
+    RewardsClaimed { player: String, amount: u64 },
+    StakeInfo { player: String, stake: u64, unbonding: Vec<(u64, u64)>, rewards: u64 }, // unbonding: (release height, amount)
+    ValidatorSlashed { validator: String, height: u64, amount: u64 },
+    ValidatorUnjailed { validator: String },
+    Pools { platform_pool: u64, community_pool: u64 },
     NftMinted { player: String, name: String, value: u64 },
     NftBought { buyer: String, seller: String, nft_index: u32 },
     NftListed { seller: String, nft_index: u32, price: u64 },
@@ -61,141 +217,810 @@ pub enum ActionResult {
     Posts { posts: Vec<(String, String, String, u64, u64)> }, // (id, creator, caption, likes, boosts)
     Videos { videos: Vec<(String, String, String, u64)> }, // (id, creator, title, views)
     NftListings { listings: Vec<(String, u32, String, u64)> }, // (seller, index, name, price)
+    PendingTransactions { transactions: Vec<PendingEntry> },
+    TransactionDropped { hash: String, dropped: bool },
 }
 
-#[derive(Debug, Default)]
-pub struct GamefiRuntime {
-    players: HashMap<String, HashMap<String, u64>>,
-    content_stats: HashMap<String, HashMap<String, u64>>,
-}
-
-impl GamefiRuntime {
-    pub fn simulate_player_activity(&mut self) {
-        for (player, tokens) in self.players.iter_mut() {
-            for (token, balance) in tokens.iter_mut() {
-                *balance += 10;
-                self.content_stats
-                    .entry(player.clone())
-                    .or_insert_with(HashMap::new)
-                    .entry(token.clone())
-                    .and_modify(|e| *e += 1)
-                    .or_insert(1);
-            }
-        }
-    }
-
-    pub fn show_leaderboard(&self) {
-        for (player, tokens) in &self.players {
-            let total: u64 = tokens.values().sum();
-            println!("Player: {}, Total Tokens: {}", player, total);
-        }
-    }
+/// How `produce_block` seals a block once its body is applied.
+enum Seal<'a> {
+    Work,
+    Authority { keypair: &'a Keypair, slot: u64, timestamp: u64 },
 }
 
-#[derive(Debug)]
+/// World state plus the chain and mempool around it. Only the world state is
+/// serialized into snapshots; blocks live in the block log and the mempool is
+/// rebuilt from the network.
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Blockchain {
-    gamefi_runtime: GamefiRuntime,
-    full_gamefi_runtime: FullGameFiRuntime,
-    nft_marketplace: HashMap<String, Vec<(u32, u64)>>,
+    runtime: GameFiRuntime,
+    #[serde(skip)]
+    chain: Chain,
+    #[serde(skip)]
+    mempool: Mempool,
+    #[serde(skip)]
+    storage: Option<Storage>,
+    #[serde(skip)]
+    committed: CommittedState,
+    #[serde(skip)]
+    undo: HashMap<String, (u64, Vec<u8>)>, // state before each recent block, by block hash, for reorgs
+    #[serde(skip)]
+    receipts: HashMap<String, (u64, String, ActionResult)>, // (height, block hash, result) by action hash
+    #[serde(skip)]
+    operator: Option<String>, // account allowed to send node-local actions
+    nft_marketplace: HashMap<String, Vec<(u32, Amount)>>,
+    content_stats: HashMap<String, HashMap<String, u64>>,
     content: HashMap<String, (String, String, u64, Vec<String>, Vec<String>)>, // (player, title, views, likes, comments)
     posts: HashMap<String, (String, String, Vec<String>, Vec<String>, u64, u64)>, // (player, caption, likes, comments, boosts, reputation_points)
     reputations: HashMap<String, u64>,
-    daily_rewards: HashMap<String, u64>,
+    nonces: HashMap<String, u64>, // number of signed actions accepted per account
+    daily_rewards: HashMap<String, Amount>,
+    faucet_claims: HashMap<String, HashMap<String, u64>>, // height of each player's last faucet request, per token
     total_users: u64,
-    platform_pool: u64,
-    community_pool: u64,
+    platform_pool: Amount,
+    community_pool: Amount,
+    collected_fees: Amount, // block producer's share of fees, paid out with the next block
 }
 
 impl Blockchain {
     pub fn new() -> Self {
         Blockchain {
-            gamefi_runtime: GamefiRuntime::default(),
-            full_gamefi_runtime: FullGameFiRuntime::new(),
+            runtime: GameFiRuntime::new(),
+            chain: Chain::new(DEFAULT_DIFFICULTY),
+            mempool: Mempool::default(),
+            storage: None,
+            committed: CommittedState::default(),
+            undo: HashMap::new(),
+            receipts: HashMap::new(),
+            operator: None,
             nft_marketplace: HashMap::new(),
+            content_stats: HashMap::new(),
             content: HashMap::new(),
             posts: HashMap::new(),
             reputations: HashMap::new(),
+            nonces: HashMap::new(),
             daily_rewards: HashMap::new(),
+            faucet_claims: HashMap::new(),
             total_users: 1000, // Simulated user base
-            platform_pool: 0,
-            community_pool: 0,
+            platform_pool: Amount::ZERO,
+            community_pool: Amount::ZERO,
+            collected_fees: Amount::ZERO,
         }
     }
 
-    fn get_mining_reward(&self, player: &str) -> u64 {
+    pub fn with_consensus(consensus: Consensus) -> Self {
+        Blockchain { chain: Chain::with_consensus(consensus), ..Blockchain::new() }
+    }
+
+    /// Opens a node's data directory, restoring the last snapshot and the
+    /// blocks up to it, or starts fresh if the directory is empty. Blocks
+    /// logged after the snapshot (a crash between the two writes) are dropped
+    /// so chain and state agree; peers will serve them again. A snapshot that
+    /// does not match the state root of the block at its height is an error.
+    ///
+    /// Undo checkpoints are kept in memory only, so a reopened node cannot
+    /// reorganize onto a branch that forks off below the height it was opened
+    /// at. Such branches are refused like ones deeper than `MAX_REORG_DEPTH`.
+    pub fn open(dir: impl AsRef<Path>) -> io::Result<Self> {
+        Self::open_with_consensus(dir, Consensus::ProofOfWork)
+    }
+
+    pub fn open_with_consensus(dir: impl AsRef<Path>, consensus: Consensus) -> io::Result<Self> {
+        let storage = Storage::open(dir)?;
+        let (height, finalized, mut blockchain) = match storage.load_snapshot::<Blockchain>()? {
+            Some(snapshot) => (snapshot.height, snapshot.finalized, snapshot.state),
+            None => (0, 0, Blockchain::new()),
+        };
+        blockchain.chain = Chain::with_consensus(consensus);
+        let mut blocks = storage.load_blocks()?;
+        if blocks.len() as u64 > height {
+            println!("Discarding {} blocks logged after the state snapshot at height {}", blocks.len() as u64 - height, height);
+            blocks.truncate(height as usize);
+            storage.rewrite_blocks(&blocks)?;
+        }
+        if (blocks.len() as u64) < height {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("State snapshot at height {} is ahead of the block log ({} blocks)", height, blocks.len())));
+        }
+        for block in blocks {
+            blockchain.chain.append(block).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        }
+        if finalized > 0 {
+            let hash = match blockchain.chain.get_by_height(finalized) {
+                Some(block) => block.hash.clone(),
+                None => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Finalized height {} is ahead of the block log", finalized))),
+            };
+            blockchain.chain.finalize(&hash).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        }
+        let head_root = &blockchain.chain.head().state_root;
+        if blockchain.state_root() == *head_root {
+            blockchain.committed = CommittedState::new(height, blockchain.state_leaves());
+        } else if height > 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("State snapshot at height {} does not match the state root {} of block {}", height, head_root, height)));
+        }
+        println!("Restored state from {} at height {}", storage.dir().display(), height);
+        blockchain.storage = Some(storage);
+        Ok(blockchain)
+    }
+
+    /// Writes a snapshot of the world state at the current height, along with
+    /// the finalized height. A no-op for in-memory blockchains created with
+    /// `new`.
+    pub fn persist(&self) -> io::Result<()> {
+        match &self.storage {
+            Some(storage) => storage.save_snapshot(self.chain.height(), self.chain.finalized_head().index, self),
+            None => Ok(()),
+        }
+    }
+
+    fn store_block(&self, block: &Block) -> io::Result<()> {
+        if let Some(storage) = &self.storage {
+            storage.append_block(block)?;
+        }
+        self.persist()
+    }
+
+    pub fn chain(&self) -> &Chain {
+        &self.chain
+    }
+
+    pub fn mempool(&self) -> &Mempool {
+        &self.mempool
+    }
+
+    /// Lets `account` send node-local actions such as `DropTransaction`.
+    /// Without an operator nobody can.
+    pub fn set_operator(&mut self, account: impl Into<String>) {
+        self.operator = Some(account.into());
+    }
+
+    /// Nonce the next signed action from `player` must carry.
+    pub fn account_nonce(&self, player: &str) -> u64 {
+        self.nonces.get(player).copied().unwrap_or(0)
+    }
+
+    /// Credits a genesis allocation of MTOSHI, creating the player if needed.
+    pub fn allocate_mtoshi(&mut self, player: &str, amount: u64) -> Result<(), GameError> {
+        self.runtime.ledger_mut().credit(player, MTOSHI, Amount::from_units(amount))?;
+        self.runtime
+            .players
+            .entry(player.to_string())
+            .or_insert_with(|| crate::runtime::Player::new(player.to_string()));
+        Ok(())
+    }
+
+    /// Every account's balances, in every token.
+    pub fn ledger(&self) -> &Ledger {
+        self.runtime.ledger()
+    }
+
+    /// Creator rewards `player` has been credited towards the daily cap.
+    pub fn daily_reward(&self, player: &str) -> Amount {
+        self.daily_rewards.get(player).copied().unwrap_or_default()
+    }
+
+    fn get_mining_reward(&self, player: &str) -> Result<Amount, GameError> {
         let base_rate = match self.total_users {
-            0..=1_000_000 => 0.1,
-            1_000_001..=10_000_000 => 0.05,
-            10_000_001..=50_000_000 => 0.01,
-            _ => 0.001,
+            0..=1_000_000 => Amount::from_units(100_000_000), // 0.1 DTOSHI
+            1_000_001..=10_000_000 => Amount::from_units(50_000_000),
+            10_000_001..=50_000_000 => Amount::from_units(10_000_000),
+            _ => Amount::from_units(1_000_000),
         };
         let reputation = self.reputations.get(player).unwrap_or(&0);
-        let multiplier = match reputation {
-            0..=100 => 1.0,
-            101..=200 => 1.2,
-            201..=300 => 1.5,
-            _ => 2.0,
+        let multiplier_percent = match reputation {
+            0..=100 => 100,
+            101..=200 => 120,
+            201..=300 => 150,
+            _ => 200,
         };
-        (base_rate * multiplier * 1_000_000_000.0) as u64 // Convert to integer (scaled)
+        base_rate.percent(multiplier_percent)
     }
 
-    fn check_daily_cap(&mut self, player: &str, reward: u64) -> u64 {
-        let current = self.daily_rewards.get(player).unwrap_or(&0);
-        let max_daily: u64 = 500 * 1_000_000_000; // 500 DTOSHI (scaled)
-        let remaining = max_daily.saturating_sub(*current);
+    fn check_daily_cap(&mut self, player: &str, reward: Amount) -> Result<Amount, GameError> {
+        let current = self.daily_rewards.get(player).copied().unwrap_or_default();
+        let max_daily = Amount::from_tokens(500)?;
+        let remaining = max_daily.checked_sub(current).unwrap_or_default();
         let allowed = reward.min(remaining);
-        self.daily_rewards
-            .entry(player.to_string())
-            .and_modify(|e| *e += allowed)
-            .or_insert(allowed);
-        allowed
+        self.daily_rewards.insert(player.to_string(), current.checked_add(allowed)?);
+        Ok(allowed)
+    }
+
+    /// First height at which `player` may request `token` from the faucet.
+    fn faucet_release(&self, player: &str, token: &str) -> u64 {
+        self.faucet_claims
+            .get(player)
+            .and_then(|claims| claims.get(token))
+            .map_or(0, |height| height + FAUCET_COOLDOWN_BLOCKS)
+    }
+
+    fn mtoshi_of(&self, player: &str) -> Amount {
+        self.ledger().balance(player, MTOSHI)
+    }
+
+    /// Adds `amount` to the player's MTOSHI. Players that do not exist are
+    /// skipped, as rewards to unknown creators always have been.
+    fn credit_mtoshi(&mut self, player: &str, amount: Amount) -> Result<(), GameError> {
+        if self.runtime.players.contains_key(player) {
+            self.runtime.ledger_mut().credit(player, MTOSHI, amount)?;
+        }
+        Ok(())
+    }
+
+    /// Takes `amount` from the player's MTOSHI, leaving it untouched if they
+    /// cannot cover it.
+    fn debit_mtoshi(&mut self, player: &str, amount: Amount) -> Result<(), GameError> {
+        self.runtime.ledger_mut().debit(player, MTOSHI, amount)?;
+        Ok(())
+    }
+
+    /// Takes `fee` from the payer and splits it the way paid likes are split:
+    /// 80% to the block producer, 10% each to the platform and community pools.
+    fn charge_fee(&mut self, payer: &str, fee: u64) -> Result<(), GameError> {
+        let fee = Amount::from_units(fee);
+        let platform_share = fee.percent(10)?;
+        let community_share = fee.percent(10)?;
+        let producer_share = fee.checked_sub(platform_share)?.checked_sub(community_share)?;
+        let platform_pool = self.platform_pool.checked_add(platform_share)?;
+        let community_pool = self.community_pool.checked_add(community_share)?;
+        let collected_fees = self.collected_fees.checked_add(producer_share)?;
+        self.debit_mtoshi(payer, fee)?;
+        self.platform_pool = platform_pool;
+        self.community_pool = community_pool;
+        self.collected_fees = collected_fees;
+        Ok(())
+    }
+
+    /// Pays the fees collected so far to the block producer, returning the
+    /// payout for the block's transaction list.
+    fn pay_collected_fees(&mut self, miner: &str) -> Result<Option<Transaction>, GameError> {
+        let fees = self.collected_fees;
+        if fees.is_zero() {
+            return Ok(None);
+        }
+        let receiver = if self.runtime.players.contains_key(miner) {
+            self.runtime.ledger_mut().credit(miner, MTOSHI, fees)?;
+            println!("{} collected {} MTOSHI in fees", miner, fees.units());
+            miner.to_string()
+        } else {
+            self.community_pool = self.community_pool.checked_add(fees)?;
+            "community_pool".to_string()
+        };
+        self.collected_fees = Amount::ZERO;
+        Ok(Some(Transaction { sender: "fees".to_string(), receiver, amount: fees.units().into(), token: MTOSHI.to_string() }))
+    }
+
+    /// The 1% of `amount` that would be burned. Nothing is burned yet.
+    fn burn_tokens(&self, amount: Amount) -> Result<Amount, GameError> {
+        amount.percent(1)
     }
 
-    fn burn_tokens(&mut self, amount: u64) -> u64 {
-        let burn = (amount as f64 * 0.01) as u64;
-        burn
+    /// Queues a signed action received over RPC or gossip. It is checked
+    /// against current state but only applied once a block includes it.
+    pub fn submit_action(&mut self, signed: SignedAction) -> Result<String, MempoolError> {
+        if let Action::RequestTokens { player, token } = &signed.action {
+            let release_height = self.faucet_release(player, token);
+            if self.chain.height() + 1 < release_height {
+                return Err(MempoolError::RateLimited { release_height });
+            }
+        }
+        let nonces = &self.nonces;
+        let ledger = self.runtime.ledger();
+        let account = |sender: &str| AccountState {
+            nonce: nonces.get(sender).copied().unwrap_or(0),
+            balance: ledger.balance(sender, MTOSHI).units(),
+        };
+        self.mempool.insert(signed, account, now())
     }
 
+    /// Processes an unsigned action. Only read-only queries are accepted;
+    /// anything that changes state or the node's mempool must be signed.
     pub fn process_action(&mut self, action: Action) -> ActionResult {
-        match action {
+        if !action.is_query() {
+            return ActionResult::Error { error: AuthError::Unsigned.into() };
+        }
+        self.apply_action(action)
+    }
+
+    /// Processes a signed action received over RPC. Under proof of work a
+    /// signed `MineBlock` produces a block with itself as the first action,
+    /// and node-local actions from the operator are applied straight away.
+    /// Anything else is queued like `submit_action` and only applied once a
+    /// block includes it; its outcome is then available from `GetReceipt`.
+    pub fn process_signed_action(&mut self, signed: SignedAction) -> Result<ActionResult, MempoolError> {
+        if signed.action.is_local() {
+            let signer = signed.verify()?;
+            if self.operator.as_deref() != Some(signer.as_str()) {
+                return Ok(ActionResult::Error { error: GameError::Unauthorized { reason: format!("{} is not this node's operator", signer) } });
+            }
+            return Ok(self.apply_action(signed.action));
+        }
+        if let Action::MineBlock { miner } = &signed.action {
+            if matches!(self.chain.consensus(), Consensus::ProofOfAuthority(_)) {
+                return Ok(ActionResult::Error { error: GameError::NotAllowed { reason: "Blocks are produced by validators in their slots".to_string() } });
+            }
+            let miner = miner.clone();
+            self.check_signed_action(&signed)?;
+            let block = self.produce_block(&miner, Some(signed), Seal::Work);
+            return Ok(ActionResult::BlockMined { miner, block });
+        }
+        let hash = self.submit_action(signed)?;
+        Ok(ActionResult::ActionQueued { hash })
+    }
+
+    /// Checks signature, nonce and fee without applying anything, returning
+    /// the signer's account id.
+    fn check_signed_action(&self, signed: &SignedAction) -> Result<String, AuthError> {
+        let signer = signed.verify()?;
+        let expected = self.account_nonce(&signer);
+        if signed.nonce != expected {
+            return Err(AuthError::InvalidNonce { expected, got: signed.nonce });
+        }
+        let fee = signed.fee();
+        let available = self.mtoshi_of(&signer).units();
+        if fee > available {
+            return Err(AuthError::InsufficientFee { needed: fee, available });
+        }
+        Ok(signer)
+    }
+
+    /// Checks and applies a signed action as the `position`-th action of a
+    /// block built on `parent_hash`, which together with the action's hash
+    /// seeds the runtime's RNG so every node gets the same outcome.
+    fn execute_signed_action(&mut self, signed: SignedAction, parent_hash: &str, position: u64) -> Result<ActionResult, AuthError> {
+        let signer = self.check_signed_action(&signed)?;
+        let fee = signed.fee();
+        self.nonces.insert(signer.clone(), signed.nonce + 1);
+        if let Err(error) = self.charge_fee(&signer, fee) {
+            return Ok(ActionResult::Error { error });
+        }
+        println!("Processing action signed by {} (nonce {}, fee {})", signer, signed.nonce, fee);
+        self.runtime.seed_rng(parent_hash, &signed.hash(), position);
+        // An action that fails part way keeps only its fee and nonce, not
+        // whatever it changed before the failing step.
+        let checkpoint = self.checkpoint();
+        let result = self.apply_action(signed.action);
+        if let ActionResult::Error { .. } = &result {
+            self.restore(&checkpoint);
+        }
+        Ok(result)
+    }
+
+    /// Root over the whole world state, as committed in each block header.
+    pub fn state_root(&self) -> String {
+        state::state_root(self.state_leaves())
+    }
+
+    fn state_leaves(&self) -> Vec<StateLeaf> {
+        let mut leaves = self.runtime.state_leaves();
+        leaves.extend(self.content_stats.iter().map(|(player, stats)| {
+            state::leaf(format!("content_stats/{}", player), &stats.iter().collect::<BTreeMap<_, _>>())
+        }));
+        leaves.extend(self.nft_marketplace.iter().map(|(seller, listings)| state::leaf(format!("listing/{}", seller), listings)));
+        leaves.extend(self.content.iter().map(|(id, content)| state::leaf(format!("content/{}", id), content)));
+        leaves.extend(self.posts.iter().map(|(id, post)| state::leaf(format!("post/{}", id), post)));
+        leaves.extend(self.reputations.iter().map(|(player, reputation)| state::leaf(format!("reputation/{}", player), reputation)));
+        leaves.extend(self.nonces.iter().map(|(player, nonce)| state::leaf(format!("nonce/{}", player), nonce)));
+        leaves.extend(self.daily_rewards.iter().map(|(player, reward)| state::leaf(format!("daily_reward/{}", player), reward)));
+        leaves.extend(self.faucet_claims.iter().map(|(player, claims)| {
+            state::leaf(format!("faucet/{}", player), &claims.iter().collect::<BTreeMap<_, _>>())
+        }));
+        leaves.push(state::leaf(
+            "pools".to_string(),
+            &(self.total_users, self.platform_pool, self.community_pool, self.collected_fees),
+        ));
+        leaves
+    }
+
+    /// Serialized world state to roll back to if a block cannot be completed.
+    fn checkpoint(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("world state serializes")
+    }
+
+    fn restore(&mut self, checkpoint: &[u8]) {
+        let mut restored: Blockchain = serde_json::from_slice(checkpoint).expect("checkpoint deserializes");
+        std::mem::swap(&mut restored.chain, &mut self.chain);
+        std::mem::swap(&mut restored.mempool, &mut self.mempool);
+        std::mem::swap(&mut restored.storage, &mut self.storage);
+        std::mem::swap(&mut restored.committed, &mut self.committed);
+        std::mem::swap(&mut restored.undo, &mut self.undo);
+        std::mem::swap(&mut restored.receipts, &mut self.receipts);
+        std::mem::swap(&mut restored.operator, &mut self.operator);
+        *self = restored;
+    }
+
+    /// Records missed slots, pays fees and block rewards and releases matured
+    /// unbonding stake once the actions of block `index` have run, returning
+    /// its transactions.
+    fn finish_block_body(&mut self, miner: &str, previous_hash: &str, index: u64, slot: u64) -> Result<Vec<Transaction>, GameError> {
+        self.record_missed_slots(miner, previous_hash, index, slot);
+        let mut transactions: Vec<Transaction> = self.pay_collected_fees(miner)?.into_iter().collect();
+        let proof_of_work = matches!(self.chain.consensus(), Consensus::ProofOfWork);
+        transactions.extend(self.runtime.distribute_block_reward(miner, previous_hash, proof_of_work)?);
+        transactions.extend(self.runtime.release_unbonded(index)?);
+        Ok(transactions)
+    }
+
+    /// Under proof of authority, counts the slots skipped between the parent
+    /// and `slot` against the validators scheduled for them, jailing any that
+    /// miss `JAIL_MISSED_SLOTS` in a row as long as another validator remains
+    /// to produce blocks. A gap of a full round or more means the whole
+    /// network stalled and is not held against anyone. Validators are jailed
+    /// as of block `index`.
+    fn record_missed_slots(&mut self, producer: &str, previous_hash: &str, index: u64, slot: u64) {
+        let Consensus::ProofOfAuthority(validators) = self.chain.consensus() else {
+            return;
+        };
+        let parent_slot = self.chain.get_block(previous_hash).map(|parent| parent.slot).unwrap_or(0);
+        if slot.saturating_sub(parent_slot) > validators.validators.len() as u64 {
+            self.runtime.staking_mut().record_produced(producer);
+            return;
+        }
+        let validators = validators.clone();
+        let staking = self.runtime.staking_mut();
+        for missed_slot in parent_slot + 1..slot {
+            let Some(missed_by) = validators.scheduled_validator(missed_slot, |id| staking.validator_weight(id)).map(str::to_string) else {
+                continue;
+            };
+            let eligible = validators.validators.iter().filter(|id| !staking.is_jailed(id)).count();
+            if staking.record_missed(&missed_by) >= JAIL_MISSED_SLOTS && eligible > 1 {
+                staking.jail(&missed_by, index);
+            }
+        }
+        staking.record_produced(producer);
+    }
+
+    /// Slashes the validator that signed both `first` and `second` at the
+    /// same height, moving the stake taken into the community pool.
+    fn punish_double_sign(&mut self, first: &Block, second: &Block) -> Result<(String, Amount), GameError> {
+        let Consensus::ProofOfAuthority(validators) = self.chain.consensus() else {
+            return Err(GameError::InvalidEvidence { reason: "Blocks are not signed by validators under proof of work".to_string() });
+        };
+        let validator = validators.verify_double_sign(first, second).map_err(|reason| GameError::InvalidEvidence { reason })?;
+        let amount = self
+            .runtime
+            .staking_mut()
+            .slash(&validator, first.index, DOUBLE_SIGN_SLASH_PERCENT)?
+            .ok_or_else(|| GameError::InvalidEvidence { reason: format!("{} was already slashed for height {}", validator, first.index) })?;
+        self.community_pool = self.community_pool.checked_add(amount)?;
+        println!("Slashed {} MTOSHI from {} for double-signing at height {}", amount.units(), validator, first.index);
+        Ok((validator, amount))
+    }
+
+    /// In proof-of-authority mode, produces and signs the block for the slot
+    /// `now` falls into, if that slot is `keypair`'s turn and has no block
+    /// yet. A validator that misses its slot just leaves it empty and the
+    /// next one builds on the latest block.
+    pub fn propose_block(&mut self, keypair: &Keypair, now: u64) -> Option<Block> {
+        let Consensus::ProofOfAuthority(validators) = self.chain.consensus() else {
+            return None;
+        };
+        let slot = validators.slot_at(now);
+        let validator = account_id(&keypair.public());
+        let staking = self.runtime.staking();
+        if validators.scheduled_validator(slot, |id| staking.validator_weight(id)) != Some(validator.as_str()) || slot <= self.chain.head().slot {
+            return None;
+        }
+        self.produce_block(&validator, None, Seal::Authority { keypair, slot, timestamp: now })
+    }
+
+    /// Builds a block on the head, starting with the producer's `coinbase`
+    /// action if there is one and followed by mempool actions by fee, applies
+    /// it, commits the resulting state root and seals it. State is rolled back
+    /// if no block is found.
+    fn produce_block(&mut self, miner: &str, coinbase: Option<SignedAction>, seal: Seal) -> Option<Block> {
+        let checkpoint = self.checkpoint();
+        let (previous_hash, index) = self.chain.next_position();
+        let mut actions = vec![];
+        let mut results = vec![];
+        if let Some(coinbase) = coinbase {
+            match self.execute_signed_action(coinbase.clone(), &previous_hash, 0) {
+                Ok(result) => results.push(result),
+                Err(e) => {
+                    println!("Rejected block producer action: {}", e);
+                    return None;
+                }
+            }
+            actions.push(coinbase);
+        }
+
+        let nonces = &self.nonces;
+        let candidates = self.mempool.select(|sender| nonces.get(sender).copied().unwrap_or(0), MAX_BLOCK_ACTIONS - actions.len());
+        for signed in candidates {
+            let position = actions.len() as u64;
+            match self.execute_signed_action(signed.clone(), &previous_hash, position) {
+                Ok(result) => {
+                    actions.push(signed);
+                    results.push(result);
+                }
+                Err(e) => println!("Leaving action {} out of block {}: {}", signed.hash(), index, e),
+            }
+        }
+        let slot = match seal {
+            Seal::Work => 0,
+            Seal::Authority { slot, .. } => slot,
+        };
+        let transactions = match self.finish_block_body(miner, &previous_hash, index, slot) {
+            Ok(transactions) => transactions,
+            Err(e) => {
+                println!("Discarding block {}: {}", index, e);
+                self.restore(&checkpoint);
+                return None;
+            }
+        };
+
+        let difficulty = self.chain.next_difficulty();
+        let state_root = self.state_root();
+        let block = match seal {
+            Seal::Work => {
+                self.runtime.set_difficulty(difficulty);
+                self.runtime.mine_block(&previous_hash, index, transactions, actions, state_root)
+            }
+            Seal::Authority { keypair, slot, timestamp } => {
+                let mut block = Block::new(index, timestamp, previous_hash, transactions, actions, state_root, difficulty);
+                block.seal(keypair, slot);
+                println!("Sealed block {} for slot {} with {} actions: {}", block.index, slot, block.actions.len(), block.hash);
+                Some(block)
+            }
+        };
+        let appended = match &block {
+            Some(block) => self.chain.append(block.clone()),
+            None => Err(format!("No block found at height {}", index)),
+        };
+        if let Err(e) = appended {
+            println!("Discarding block {}: {}", index, e);
+            self.restore(&checkpoint);
+            return None;
+        }
+        let block = block?;
+        self.remember_undo(&block, checkpoint);
+        self.after_block(&block, results);
+        Some(block)
+    }
+
+    /// Imports a block from another node. A block extending the head is
+    /// replayed on it and refused, with state left untouched, if any action
+    /// fails or the resulting transactions or state root differ from the ones
+    /// it commits to. A block on another branch is kept aside, and once its
+    /// branch has more cumulative work than the main chain the node
+    /// reorganizes onto it.
+    pub fn import_block(&mut self, block: Block) -> Result<(), String> {
+        if block.previous_hash != self.chain.head().hash {
+            let hash = block.hash.clone();
+            self.chain.add_fork(block)?;
+            if self.chain.work_of(&hash).unwrap_or(0) > self.chain.work() {
+                return self.reorganize(&hash);
+            }
+            return Ok(());
+        }
+        let results = self.connect_block(&block)?;
+        self.after_block(&block, results);
+        Ok(())
+    }
+
+    /// Replays `block` on the head and appends it, keeping the state before
+    /// it so a reorg can roll it back. Returns the outcome of each action.
+    fn connect_block(&mut self, block: &Block) -> Result<Vec<ActionResult>, String> {
+        self.chain.validate_next(block)?;
+        let miner = self.block_producer(block)?;
+
+        let checkpoint = self.checkpoint();
+        let results = match self.replay_block(block, &miner).and_then(|results| self.chain.append(block.clone()).map(|_| results)) {
+            Ok(results) => results,
+            Err(e) => {
+                self.restore(&checkpoint);
+                return Err(e);
+            }
+        };
+        self.remember_undo(block, checkpoint);
+        Ok(results)
+    }
+
+    /// Marks `hash` final once the finality gadget reports a precommit
+    /// quorum for it, first switching to its branch if it is not on the main
+    /// chain. Final blocks are never reorganized away, including after a
+    /// restart: the finalized height is persisted with the snapshot.
+    pub fn finalize(&mut self, hash: &str) -> Result<(), String> {
+        if !self.chain.is_main(hash) {
+            if !self.chain.contains(hash) {
+                return Err(format!("Unknown block {}", hash));
+            }
+            self.reorganize(hash)?;
+        }
+        self.chain.finalize(hash)?;
+        self.persist().map_err(|e| format!("Failed to persist finalized block {}: {}", hash, e))
+    }
+
+    /// Account paid for producing `block`: the miner named by its leading
+    /// `MineBlock` under proof of work, the signing validator under proof of
+    /// authority, where blocks carry no `MineBlock` at all.
+    fn block_producer(&self, block: &Block) -> Result<String, String> {
+        let mine_actions = block.actions.iter().filter(|signed| matches!(signed.action, Action::MineBlock { .. })).count();
+        match self.chain.consensus() {
+            Consensus::ProofOfWork => match block.actions.first().map(|signed| &signed.action) {
+                Some(Action::MineBlock { miner }) if mine_actions == 1 => Ok(miner.clone()),
+                Some(Action::MineBlock { .. }) => Err("Block contains more than one MineBlock action".to_string()),
+                _ => Err("Block does not start with its producer's MineBlock action".to_string()),
+            },
+            Consensus::ProofOfAuthority(_) if mine_actions > 0 => Err("Proof-of-authority blocks cannot contain MineBlock actions".to_string()),
+            Consensus::ProofOfAuthority(validators) => {
+                let signer = block.verify_seal()?;
+                let staking = self.runtime.staking();
+                match validators.scheduled_validator(block.slot, |id| staking.validator_weight(id)) {
+                    Some(expected) if expected == signer => Ok(signer),
+                    Some(expected) => Err(format!("Block {} is signed by {}, but slot {} belongs to {}", block.index, signer, block.slot, expected)),
+                    None => Err("Validator set is empty".to_string()),
+                }
+            }
+        }
+    }
+
+    /// State committed by the main-chain block at `height`, rebuilt from the
+    /// undo checkpoint taken before the block after it. `None` once that
+    /// checkpoint has been dropped or for heights past the head.
+    fn committed_at(&self, height: u64) -> Option<CommittedState> {
+        let next = self.chain.get_by_height(height + 1)?;
+        let (_, checkpoint) = self.undo.get(&next.hash)?;
+        let state: Blockchain = serde_json::from_slice(checkpoint).expect("checkpoint deserializes");
+        Some(CommittedState::new(height, state.state_leaves()))
+    }
+
+    fn remember_undo(&mut self, block: &Block, checkpoint: Vec<u8>) {
+        let height = block.index;
+        self.undo.insert(block.hash.clone(), (height, checkpoint));
+        self.undo.retain(|_, (index, _)| *index + MAX_REORG_DEPTH > height);
+    }
+
+    /// Switches the main chain to the branch ending in `tip`: rolls state
+    /// back to the fork point, replays the branch and returns the actions of
+    /// the disconnected blocks to the mempool. If a branch block turns out to
+    /// be invalid it is dropped, with its descendants, and the node stays on
+    /// its previous chain.
+    fn reorganize(&mut self, tip: &str) -> Result<(), String> {
+        let (fork_height, branch) = self
+            .chain
+            .branch(tip)
+            .ok_or_else(|| format!("Branch ending in {} does not connect to the main chain", tip))?;
+        if fork_height < self.chain.finalized_head().index {
+            return Err(format!("Branch ending in {} forks off below the finalized head", tip));
+        }
+        let first_disconnected = self
+            .chain
+            .get_by_height(fork_height + 1)
+            .map(|block| block.hash.clone())
+            .ok_or_else(|| format!("Branch ending in {} does not fork off the main chain", tip))?;
+        let Some((_, rollback)) = self.undo.get(&first_disconnected).cloned() else {
+            return Err(format!("State before block {} is no longer available", first_disconnected));
+        };
+        println!("Reorganizing from {} to {} (fork at height {})", self.chain.head().hash, tip, fork_height);
+
+        let backup = self.checkpoint();
+        self.restore(&rollback);
+        let disconnected = self.chain.disconnect_to(fork_height);
+        let mut receipts = vec![];
+        for block in &branch {
+            match self.connect_block(block) {
+                Ok(results) => receipts.push((block, results)),
+                Err(e) => {
+                    println!("Abandoning reorg, block {} is invalid: {}", block.hash, e);
+                    self.chain.remove_fork(&block.hash);
+                    self.chain.disconnect_to(fork_height);
+                    for block in &disconnected {
+                        self.chain.append(block.clone()).expect("previous main chain is still valid");
+                    }
+                    self.restore(&backup);
+                    return Err(e);
+                }
+            }
+        }
+
+        self.receipts.retain(|_, (_, block_hash, _)| disconnected.iter().all(|block| &block.hash != block_hash));
+        for (block, results) in receipts {
+            self.record_receipts(block, results);
+        }
+        for block in &disconnected {
+            // Only the producer's own block action is tied to the orphaned block.
+            for signed in block.actions.iter().filter(|signed| !matches!(signed.action, Action::MineBlock { .. })).cloned() {
+                if let Err(e) = self.submit_action(signed) {
+                    println!("Dropping action from orphaned block {}: {}", block.index, e);
+                }
+            }
+        }
+        let head = self.chain.head().clone();
+        self.committed = CommittedState::new(head.index, self.state_leaves());
+        let nonces = &self.nonces;
+        self.mempool.prune(|sender| nonces.get(sender).copied().unwrap_or(0), now());
+        if let Some(storage) = &self.storage {
+            if let Err(e) = storage.rewrite_blocks(&self.chain.blocks()[1..]).and_then(|_| self.persist()) {
+                println!("Failed to persist reorg to block {}: {}", head.index, e);
+            }
+        }
+        Ok(())
+    }
+
+    fn replay_block(&mut self, block: &Block, miner: &str) -> Result<Vec<ActionResult>, String> {
+        let mut results = vec![];
+        for (position, signed) in block.actions.iter().cloned().enumerate() {
+            let result = self
+                .execute_signed_action(signed, &block.previous_hash, position as u64)
+                .map_err(|e| format!("Action {} of block {} is invalid: {}", position, block.index, e))?;
+            results.push(result);
+        }
+        let transactions = self
+            .finish_block_body(miner, &block.previous_hash, block.index, block.slot)
+            .map_err(|e| format!("Block {} cannot be completed: {}", block.index, e))?;
+        if Block::transactions_root(&transactions) != block.tx_root {
+            return Err(format!("Block {} pays out different rewards than its transactions", block.index));
+        }
+        let state_root = self.state_root();
+        if state_root != block.state_root {
+            return Err(format!("Block {} commits to state root {}, but applying it gives {}", block.index, block.state_root, state_root));
+        }
+        Ok(results)
+    }
+
+    /// Records the state a newly appended block commits to and the outcome
+    /// of each of its actions, drops its actions from the mempool and
+    /// persists it.
+    fn after_block(&mut self, block: &Block, results: Vec<ActionResult>) {
+        self.committed = CommittedState::new(block.index, self.state_leaves());
+        self.record_receipts(block, results);
+        let nonces = &self.nonces;
+        self.mempool.prune(|sender| nonces.get(sender).copied().unwrap_or(0), now());
+        if let Err(e) = self.store_block(block) {
+            println!("Failed to persist block {}: {}", block.index, e);
+        }
+    }
+
+    /// Keeps the outcome of each action in `block`, by action hash, and
+    /// forgets those older than `RECEIPT_RETENTION_BLOCKS`.
+    fn record_receipts(&mut self, block: &Block, results: Vec<ActionResult>) {
+        for (signed, result) in block.actions.iter().zip(results) {
+            self.receipts.insert(signed.hash(), (block.index, block.hash.clone(), result));
+        }
+        self.receipts.retain(|_, (height, _, _)| *height + RECEIPT_RETENTION_BLOCKS > block.index);
+    }
+
+    fn apply_action(&mut self, action: Action) -> ActionResult {
+        self.try_apply_action(action).unwrap_or_else(|error| ActionResult::Error { error })
+    }
+
+    fn try_apply_action(&mut self, action: Action) -> Result<ActionResult, GameError> {
+        let result = match action {
             Action::RequestTokens { player, token } => {
-                let amount = 1000;
-                self.gamefi_runtime
-                    .players
-                    .entry(player.clone())
-                    .or_insert_with(HashMap::new)
-                    .entry(token.clone())
-                    .and_modify(|e| *e += amount)
-                    .or_insert(amount);
-                if !self.full_gamefi_runtime.players.contains_key(&player) {
-                    self.full_gamefi_runtime.players.insert(player.clone(), crate::runtime::Player::new(player.clone()));
+                let height = self.chain.height() + 1;
+                let release_height = self.faucet_release(&player, &token);
+                if height < release_height {
+                    return Err(GameError::NotAllowed { reason: format!("{} can request {} again from block {}", player, token, release_height) });
                 }
-                ActionResult::TokensRequested { player, token, amount }
+                self.runtime.ledger_mut().credit(&player, &token, FAUCET_AMOUNT)?;
+                self.faucet_claims.entry(player.clone()).or_default().insert(token.clone(), height);
+                if !self.runtime.players.contains_key(&player) {
+                    self.runtime.players.insert(player.clone(), crate::runtime::Player::new(player.clone()));
+                }
+                ActionResult::TokensRequested { player, token, amount: FAUCET_AMOUNT.units() }
             }
             Action::GetBalance { player, token } => {
-                let balance = self.gamefi_runtime
-                    .players
-                    .get(&player)
-                    .and_then(|tokens| tokens.get(&token))
-                    .copied()
-                    .unwrap_or(0);
+                ledger::token(&token)?;
+                let balance = self.ledger().balance(&player, &token).units();
                 ActionResult::Balance { player, token, balance }
             }
+            Action::GetTokens => {
+                let tokens = ledger::TOKENS.iter().map(|token| (token.id.to_string(), token.decimals)).collect();
+                ActionResult::Tokens { tokens }
+            }
             Action::GetPlayer { player } => {
-                let data = self.gamefi_runtime
-                    .players
-                    .get(&player)
+                let data = self
+                    .ledger()
+                    .balances(&player)
                     .map(|tokens| {
                         tokens
                             .iter()
-                            .map(|(k, v)| (k.clone(), v.to_string()))
+                            .map(|(k, v)| (k.clone(), v.units().to_string()))
                             .collect::<HashMap<_, _>>()
                     })
                     .unwrap_or_default();
                 ActionResult::Player { player, data }
             }
             Action::GetContentStats { player } => {
-                let stats = self.gamefi_runtime
+                let stats = self
                     .content_stats
                     .get(&player)
                     .cloned()
@@ -203,15 +1028,59 @@ impl Blockchain {
                 ActionResult::ContentStats { player, stats }
             }
             Action::SimulateActivity => {
-                self.full_gamefi_runtime.simulate_player_activity();
+                self.runtime.simulate_player_activity();
                 ActionResult::ActivitySimulated
             }
-            Action::MineBlock { miner, index } => {
-                let block = self.full_gamefi_runtime.mine_block("previous_hash", miner.clone(), index);
-                ActionResult::BlockMined { miner, block }
+            // A block's leading MineBlock only pays its fee and bumps the
+            // producer's nonce; the block itself is built by `produce_block`.
+            Action::MineBlock { miner } => ActionResult::BlockMined { miner, block: None },
+            Action::GetDifficulty => {
+                ActionResult::Difficulty { height: self.chain.height(), difficulty: self.chain.next_difficulty() }
+            }
+            Action::GetTransactionProof { height, tx_index } => {
+                let block = self.chain.get_by_height(height).ok_or_else(|| GameError::not_found("Block", height.to_string()))?;
+                match (block.transactions.get(tx_index as usize), block.transaction_proof(tx_index as usize)) {
+                    (Some(transaction), Some(proof)) => ActionResult::TransactionProof {
+                        height,
+                        tx_root: block.tx_root.clone(),
+                        transaction: transaction.clone(),
+                        proof,
+                    },
+                    _ => return Err(GameError::not_found("Transaction", format!("{}/{}", height, tx_index))),
+                }
+            }
+            Action::GetStateRoot { height } => match self.chain.get_by_height(height) {
+                Some(block) => ActionResult::StateRoot { height, state_root: block.state_root.clone() },
+                None => return Err(GameError::not_found("Block", height.to_string())),
+            },
+            Action::GetAccountProof { player, height } => {
+                let historical;
+                let committed = match height {
+                    Some(height) if height != self.committed.height() => {
+                        historical = self.committed_at(height).ok_or_else(|| GameError::not_found("State at height", height.to_string()))?;
+                        &historical
+                    }
+                    _ => &self.committed,
+                };
+                let not_found = || GameError::not_found("Player", player.clone());
+                let proof = AccountProof {
+                    height: committed.height(),
+                    state_root: committed.root().to_string(),
+                    player: committed.prove(&format!("player/{}", player)).ok_or_else(not_found)?,
+                    balances: committed.prove(&format!("balance/{}", player)).ok_or_else(not_found)?,
+                };
+                ActionResult::AccountProof { proof }
+            }
+            Action::GetFinalizedHead => {
+                let head = self.chain.finalized_head();
+                ActionResult::FinalizedHead { height: head.index, hash: head.hash.clone() }
+            }
+            Action::GetReceipt { hash } => {
+                let (height, block_hash, result) = self.receipts.get(&hash).cloned().ok_or_else(|| GameError::not_found("Receipt", hash.clone()))?;
+                ActionResult::Receipt { hash, height, block_hash, result: Box::new(result) }
             }
             Action::RecordActivity { player, activity_type } => {
-                self.full_gamefi_runtime.record_activity(&player, activity_type);
+                self.runtime.record_activity(&player, activity_type);
                 let points = match activity_type {
                     1 => 10,
                     2 => 15,
@@ -222,285 +1091,256 @@ impl Blockchain {
                 ActionResult::ActivityRecorded { player, activity_type, points }
             }
             Action::Battle { player1, player2 } => {
-                self.full_gamefi_runtime.battle(&player1, &player2);
-                ActionResult::BattleResult { winner: player1, loser: player2 }
+                let winner = self.runtime.battle(&player1, &player2);
+                let (winner, loser) = if winner == player1 { (player1, player2) } else { (player2, player1) };
+                ActionResult::BattleResult { winner, loser }
             }
             Action::TradeItems { player1, player1_item_index, player2, player2_item_index } => {
-                let success = self.full_gamefi_runtime.trade_items(&player1, player1_item_index as usize, &player2, player2_item_index as usize);
+                let success = self.runtime.trade_items(&player1, player1_item_index as usize, &player2, player2_item_index as usize);
                 ActionResult::ItemsTraded { player1, player2, success }
             }
             Action::DistributeMtoshiRewards => {
-                self.full_gamefi_runtime.distribute_mtoshi_rewards();
-                let total_reward = 500;
-                ActionResult::MtoshiDistributed { total_reward }
+                let total_reward = self.runtime.distribute_mtoshi_rewards()?;
+                ActionResult::MtoshiDistributed { total_reward: total_reward.units() }
             }
             Action::ShowLeaderboard => {
-                self.full_gamefi_runtime.show_leaderboard();
-                let players = self.full_gamefi_runtime.players.iter()
-                    .map(|(id, p)| (id.clone(), p.level, p.experience, p.solutio_balance, p.mtoshi_balance))
+                self.runtime.show_leaderboard();
+                let ledger = self.runtime.ledger();
+                let players = self.runtime.players.iter()
+                    .map(|(id, p)| (id.clone(), p.level, p.experience, ledger.balance(id, SOLUTIO).units(), ledger.balance(id, MTOSHI).units()))
                     .collect();
                 ActionResult::Leaderboard { players }
             }
             Action::GetMtoshiBalance { player } => {
-                let balance = self.full_gamefi_runtime.get_mtoshi_balance(&player).unwrap_or(0);
+                let balance = self.mtoshi_of(&player).units();
                 ActionResult::MtoshiBalance { player, balance }
             }
-            Action::MintNft { player, name, value } => {
-                let cost = 10 * 1_000_000_000; // 10 DTOSHI
-                if self.full_gamefi_runtime.players.get_mut(&player).map(|p| p.mtoshi_balance >= cost).unwrap_or(false) {
-                    let player_ref = self.full_gamefi_runtime.players.get_mut(&player).unwrap();
-                    player_ref.mtoshi_balance -= cost;
-                    player_ref.add_item(crate::runtime::Item { name: name.clone(), value });
-                    self.burn_tokens(cost);
-                    println!("{} minted NFT {} (value: {}) for {} DTOSHI", player, name, value, cost / 1_000_000_000);
-                    ActionResult::NftMinted { player, name, value }
-                } else {
-                    ActionResult::Error { message: "Insufficient DTOSHI".to_string() }
+            Action::GetNonce { player } => {
+                let nonce = self.account_nonce(&player);
+                ActionResult::Nonce { player, nonce }
+            }
+            Action::Stake { player, amount } => {
+                let stake = self.runtime.stake(&player, Amount::from_units(amount))?;
+                ActionResult::Staked { player, amount, stake: stake.units() }
+            }
+            Action::Unstake { player, amount } => {
+                // Actions run as part of the block after the current head.
+                let height = self.chain.height() + 1;
+                let release_height = self.runtime.unstake(&player, Amount::from_units(amount), height)?;
+                ActionResult::Unstaked { player, amount, release_height }
+            }
+            Action::ClaimRewards { player } => {
+                let amount = self.runtime.claim_rewards(&player)?.units();
+                ActionResult::RewardsClaimed { player, amount }
+            }
+            Action::GetStake { player } => {
+                let staking = self.runtime.staking();
+                ActionResult::StakeInfo {
+                    stake: staking.stake_of(&player).units(),
+                    unbonding: staking.unbonding_of(&player).into_iter().map(|(height, amount)| (height, amount.units())).collect(),
+                    rewards: staking.rewards_of(&player).units(),
+                    player,
+                }
+            }
+            Action::ReportDoubleSign { first, second } => {
+                let (validator, amount) = self.punish_double_sign(&first, &second)?;
+                ActionResult::ValidatorSlashed { validator, height: first.index, amount: amount.units() }
+            }
+            Action::Unjail { validator } => {
+                let release_height = self
+                    .runtime
+                    .staking()
+                    .jail_release(&validator)
+                    .ok_or_else(|| GameError::NotAllowed { reason: format!("{} is not jailed", validator) })?;
+                let height = self.chain.height() + 1;
+                if height < release_height {
+                    return Err(GameError::NotAllowed { reason: format!("{} stays jailed until block {}", validator, release_height) });
                 }
+                self.runtime.staking_mut().unjail(&validator);
+                ActionResult::ValidatorUnjailed { validator }
+            }
+            Action::GetPools => ActionResult::Pools { platform_pool: self.platform_pool.units(), community_pool: self.community_pool.units() },
+            Action::MintNft { player, name, value } => {
+                let cost = Amount::from_tokens(10)?;
+                self.debit_mtoshi(&player, cost)?;
+                let player_ref = self.runtime.players.get_mut(&player).expect("debited player exists");
+                player_ref.add_item(crate::runtime::Item { name: name.clone(), value });
+                self.burn_tokens(cost)?;
+                println!("{} minted NFT {} (value: {}) for {} DTOSHI", player, name, value, cost);
+                ActionResult::NftMinted { player, name, value }
             }
             Action::BuyNft { buyer, seller, nft_index } => {
                 let price = self.nft_marketplace.get(&seller).and_then(|listings| listings.iter().find(|(i, _)| *i == nft_index)).map(|(_, p)| *p);
-                if let Some(price) = price {
-                    if self.full_gamefi_runtime.players.get(&buyer).map(|p| p.mtoshi_balance >= price).unwrap_or(false) {
-                        if self.full_gamefi_runtime.players.contains_key(&seller) && (nft_index as usize) < self.full_gamefi_runtime.players[&seller].inventory.len() {
-                            let nft = self.full_gamefi_runtime.players.get_mut(&seller).unwrap().inventory.remove(nft_index as usize);
-                            self.full_gamefi_runtime.players.get_mut(&buyer).unwrap().inventory.push(nft.clone());
-                            self.full_gamefi_runtime.players.get_mut(&buyer).unwrap().mtoshi_balance -= price;
-                            self.full_gamefi_runtime.players.get_mut(&seller).unwrap().mtoshi_balance += price;
-                            self.nft_marketplace.get_mut(&seller).unwrap().retain(|(i, _)| *i != nft_index);
-                            self.burn_tokens(price);
-                            println!("{} bought NFT {} from {} for {} DTOSHI", buyer, nft.name, seller, price / 1_000_000_000);
-                            ActionResult::NftBought { buyer, seller, nft_index }
-                        } else {
-                            ActionResult::Error { message: "Seller or NFT not found".to_string() }
-                        }
-                    } else {
-                        ActionResult::Error { message: "Insufficient DTOSHI".to_string() }
-                    }
-                } else {
-                    ActionResult::Error { message: "NFT not listed".to_string() }
+                let price = price.ok_or_else(|| GameError::not_found("Listing", format!("{}/{}", seller, nft_index)))?;
+                self.ledger().require(&buyer, MTOSHI, price)?;
+                let listed_nft_exists = self.runtime.players.get(&seller).is_some_and(|p| (nft_index as usize) < p.inventory.len());
+                if !listed_nft_exists {
+                    return Err(GameError::not_found("NFT", format!("{}/{}", seller, nft_index)));
                 }
+                // Credit first: it is the step that can overflow, and the buyer's balance was checked above.
+                self.credit_mtoshi(&seller, price)?;
+                self.debit_mtoshi(&buyer, price)?;
+                let nft = self.runtime.players.get_mut(&seller).unwrap().inventory.remove(nft_index as usize);
+                self.runtime.players.get_mut(&buyer).unwrap().inventory.push(nft.clone());
+                self.nft_marketplace.get_mut(&seller).unwrap().retain(|(i, _)| *i != nft_index);
+                self.burn_tokens(price)?;
+                println!("{} bought NFT {} from {} for {} DTOSHI", buyer, nft.name, seller, price);
+                ActionResult::NftBought { buyer, seller, nft_index }
             }
             Action::SellNft { seller, nft_index, price } => {
-                if let Some(player) = self.full_gamefi_runtime.players.get(&seller) {
-                    if (nft_index as usize) < player.inventory.len() {
-                        self.nft_marketplace
-                            .entry(seller.clone())
-                            .or_insert_with(Vec::new)
-                            .push((nft_index, price));
-                        println!("NFT {} listed for sale by {} at {} DTOSHI", nft_index, seller, price / 1_000_000_000);
-                        ActionResult::NftListed { seller, nft_index, price }
-                    } else {
-                        ActionResult::Error { message: "Invalid NFT index".to_string() }
-                    }
-                } else {
-                    ActionResult::Error { message: "Seller not found".to_string() }
+                let player = self.runtime.players.get(&seller).ok_or_else(|| GameError::not_found("Player", seller.clone()))?;
+                if (nft_index as usize) >= player.inventory.len() {
+                    return Err(GameError::not_found("NFT", format!("{}/{}", seller, nft_index)));
                 }
+                let listing_price = Amount::from_units(price);
+                self.nft_marketplace
+                    .entry(seller.clone())
+                    .or_default()
+                    .push((nft_index, listing_price));
+                println!("NFT {} listed for sale by {} at {} DTOSHI", nft_index, seller, listing_price);
+                ActionResult::NftListed { seller, nft_index, price }
             }
             Action::UploadContent { player, content_id, title } => {
-                let cost = 5 * 1_000_000_000; // 5 DTOSHI
-                if self.full_gamefi_runtime.players.get_mut(&player).map(|p| p.mtoshi_balance >= cost).unwrap_or(false) {
-                    self.full_gamefi_runtime.players.get_mut(&player).unwrap().mtoshi_balance -= cost;
-                    self.content.insert(content_id.clone(), (player.clone(), title.clone(), 0, vec![], vec![]));
-                    self.burn_tokens(cost);
-                    self.reputations
-                        .entry(player.clone())
-                        .and_modify(|e| *e += 5)
-                        .or_insert(5);
-                    println!("{} uploaded content {}: {}", player, content_id, title);
-                    ActionResult::ContentUploaded { player, content_id }
-                } else {
-                    ActionResult::Error { message: "Insufficient DTOSHI".to_string() }
-                }
+                let cost = Amount::from_tokens(5)?;
+                self.debit_mtoshi(&player, cost)?;
+                self.content.insert(content_id.clone(), (player.clone(), title.clone(), 0, vec![], vec![]));
+                self.burn_tokens(cost)?;
+                self.reputations
+                    .entry(player.clone())
+                    .and_modify(|e| *e += 5)
+                    .or_insert(5);
+                println!("{} uploaded content {}: {}", player, content_id, title);
+                ActionResult::ContentUploaded { player, content_id }
             }
             Action::LikeContent { player, content_id } => {
-                if let Some((creator, _, _, likes, _)) = self.content.get(&content_id) {
-                    if !likes.contains(&player) {
-                        let creator = creator.clone();
-                        let reward = self.check_daily_cap(&creator, 1 * 1_000_000_000);
-                        let content_id = content_id.clone();
-                        let player = player.clone();
-                        if let Some((_, _, _, likes, _)) = self.content.get_mut(&content_id) {
-                            likes.push(player.clone());
-                        }
-                        if let Some(creator_player) = self.full_gamefi_runtime.players.get_mut(&creator) {
-                            creator_player.mtoshi_balance += reward;
-                            self.burn_tokens(reward);
-                        }
-                        self.reputations
-                            .entry(player.clone())
-                            .and_modify(|e| *e += 1)
-                            .or_insert(1);
-                        println!("{} liked content {}", player, content_id);
-                        ActionResult::ContentLiked { player, content_id }
-                    } else {
-                        ActionResult::Error { message: "Already liked".to_string() }
-                    }
-                } else {
-                    ActionResult::Error { message: "Content not found".to_string() }
+                let (creator, _, _, likes, _) = self.content.get(&content_id).ok_or_else(|| GameError::not_found("Content", content_id.clone()))?;
+                if likes.contains(&player) {
+                    return Err(GameError::AlreadyLiked { kind: "Content".to_string(), id: content_id });
+                }
+                let creator = creator.clone();
+                let reward = self.check_daily_cap(&creator, Amount::from_tokens(1)?)?;
+                self.credit_mtoshi(&creator, reward)?;
+                self.burn_tokens(reward)?;
+                if let Some((_, _, _, likes, _)) = self.content.get_mut(&content_id) {
+                    likes.push(player.clone());
                 }
+                self.reputations
+                    .entry(player.clone())
+                    .and_modify(|e| *e += 1)
+                    .or_insert(1);
+                println!("{} liked content {}", player, content_id);
+                ActionResult::ContentLiked { player, content_id }
             }
             Action::CommentContent { player, comment, content_id } => {
-                if let Some((creator, _, _, _, _comments)) = self.content.get(&content_id) {
-                    let creator = creator.clone();
-                    let reward = self.check_daily_cap(&creator, 2 * 1_000_000_000);
-                    let content_id = content_id.clone();
-                    let player = player.clone();
-                    if let Some((_, _, _, _, comments)) = self.content.get_mut(&content_id) {
-                        comments.push(comment.clone());
-                    }
-                    if let Some(creator_player) = self.full_gamefi_runtime.players.get_mut(&creator) {
-                        creator_player.mtoshi_balance += reward;
-                        self.burn_tokens(reward);
-                    }
-                    self.reputations
-                        .entry(player.clone())
-                        .and_modify(|e| *e += 1)
-                        .or_insert(1);
-                    println!("{} commented on content {}: {}", player, content_id, comment);
-                    ActionResult::ContentCommented { player, content_id }
-                } else {
-                    ActionResult::Error { message: "Content not found".to_string() }
+                let (creator, _, _, _, _) = self.content.get(&content_id).ok_or_else(|| GameError::not_found("Content", content_id.clone()))?;
+                let creator = creator.clone();
+                let reward = self.check_daily_cap(&creator, Amount::from_tokens(2)?)?;
+                self.credit_mtoshi(&creator, reward)?;
+                self.burn_tokens(reward)?;
+                if let Some((_, _, _, _, comments)) = self.content.get_mut(&content_id) {
+                    comments.push(comment.clone());
                 }
+                self.reputations
+                    .entry(player.clone())
+                    .and_modify(|e| *e += 1)
+                    .or_insert(1);
+                println!("{} commented on content {}: {}", player, content_id, comment);
+                ActionResult::ContentCommented { player, content_id }
             }
             Action::ViewContent { player, content_id } => {
-                if let Some((creator, _, _views, _, _)) = self.content.get(&content_id) {
-                    let creator = creator.clone();
-                    let reward = self.check_daily_cap(&creator, self.get_mining_reward(&player));
-                    let content_id = content_id.clone();
-                    let player = player.clone();
-                    if let Some((_, _, views, _, _)) = self.content.get_mut(&content_id) {
-                        *views += 1;
-                    }
-                    if let Some(creator_player) = self.full_gamefi_runtime.players.get_mut(&creator) {
-                        creator_player.mtoshi_balance += reward;
-                        self.burn_tokens(reward);
-                    }
-                    println!("{} viewed content {}", player, content_id);
-                    ActionResult::ContentViewed { player, content_id }
-                } else {
-                    ActionResult::Error { message: "Content not found".to_string() }
+                let (creator, _, _, _, _) = self.content.get(&content_id).ok_or_else(|| GameError::not_found("Content", content_id.clone()))?;
+                let creator = creator.clone();
+                let reward = self.check_daily_cap(&creator, self.get_mining_reward(&player)?)?;
+                self.credit_mtoshi(&creator, reward)?;
+                self.burn_tokens(reward)?;
+                if let Some((_, _, views, _, _)) = self.content.get_mut(&content_id) {
+                    *views += 1;
                 }
+                println!("{} viewed content {}", player, content_id);
+                ActionResult::ContentViewed { player, content_id }
             }
             Action::PostContent { player, post_id, caption } => {
-                let cost = 3 * 1_000_000_000; // 3 DTOSHI
-                if self.full_gamefi_runtime.players.get_mut(&player).map(|p| p.mtoshi_balance >= cost).unwrap_or(false) {
-                    self.full_gamefi_runtime.players.get_mut(&player).unwrap().mtoshi_balance -= cost;
-                    self.posts.insert(post_id.clone(), (player.clone(), caption.clone(), vec![], vec![], 0, 0));
-                    self.burn_tokens(cost);
-                    self.reputations
-                        .entry(player.clone())
-                        .and_modify(|e| *e += 5)
-                        .or_insert(5);
-                    println!("{} posted content {}: {}", player, post_id, caption);
-                    ActionResult::ContentPosted { player, post_id }
-                } else {
-                    ActionResult::Error { message: "Insufficient DTOSHI".to_string() }
-                }
+                let cost = Amount::from_tokens(3)?;
+                self.debit_mtoshi(&player, cost)?;
+                self.posts.insert(post_id.clone(), (player.clone(), caption.clone(), vec![], vec![], 0, 0));
+                self.burn_tokens(cost)?;
+                self.reputations
+                    .entry(player.clone())
+                    .and_modify(|e| *e += 5)
+                    .or_insert(5);
+                println!("{} posted content {}: {}", player, post_id, caption);
+                ActionResult::ContentPosted { player, post_id }
             }
             Action::LikePost { player, post_id, paid } => {
-                if let Some((creator, _, likes, _, _, _reputation_points)) = self.posts.get(&post_id) {
-                    if !likes.contains(&player) {
-                        let creator = creator.clone();
-                        let post_id = post_id.clone();
-                        let player = player.clone();
-                        let cost = if paid { 0.05 * 1_000_000_000 as f64 } else { 0.0 }; // 0.05 DTOSHI
-                        let creator_share = if paid { (cost * 0.8) as u64 } else { 0 };
-                        let platform_share = if paid { (cost * 0.1) as u64 } else { 0 };
-                        let community_share = if paid { (cost * 0.1) as u64 } else { 0 };
-                        let reward = if paid { self.check_daily_cap(&creator, creator_share) } else { self.check_daily_cap(&creator, 1 * 1_000_000_000) };
-                        let burn = if paid { self.burn_tokens(cost as u64) } else { 0 };
-                        let reputation_increase = if paid { 2 } else { 1 };
-                        if paid && !self.full_gamefi_runtime.players.get(&player).map(|p| p.mtoshi_balance >= cost as u64).unwrap_or(false) {
-                            return ActionResult::Error { message: "Insufficient DTOSHI".to_string() };
-                        }
-                        if let Some((_, _, likes, _, _, reputation_points)) = self.posts.get_mut(&post_id) {
-                            likes.push(player.clone());
-                            *reputation_points += 1;
-                        }
-                        if paid {
-                            self.full_gamefi_runtime.players.get_mut(&player).unwrap().mtoshi_balance -= cost as u64;
-                            self.platform_pool += platform_share;
-                            self.community_pool += community_share;
-                        }
-                        if let Some(creator_player) = self.full_gamefi_runtime.players.get_mut(&creator) {
-                            creator_player.mtoshi_balance += reward;
-                        }
-                        if burn > 0 {
-                            self.burn_tokens(burn);
-                        }
-                        self.reputations
-                            .entry(player.clone())
-                            .and_modify(|e| *e += reputation_increase)
-                            .or_insert(reputation_increase);
-                        println!("{} liked post {} (paid: {})", player, post_id, paid);
-                        ActionResult::PostLiked { player, post_id, paid }
-                    } else {
-                        ActionResult::Error { message: "Already liked".to_string() }
-                    }
-                } else {
-                    ActionResult::Error { message: "Post not found".to_string() }
+                let (creator, _, likes, _, _, _) = self.posts.get(&post_id).ok_or_else(|| GameError::not_found("Post", post_id.clone()))?;
+                if likes.contains(&player) {
+                    return Err(GameError::AlreadyLiked { kind: "Post".to_string(), id: post_id });
+                }
+                let creator = creator.clone();
+                let cost = if paid { PAID_LIKE_COST } else { Amount::ZERO };
+                self.ledger().require(&player, MTOSHI, cost)?;
+                let platform_pool = self.platform_pool.checked_add(cost.percent(10)?)?;
+                let community_pool = self.community_pool.checked_add(cost.percent(10)?)?;
+                let reward = if paid { cost.percent(80)? } else { Amount::from_tokens(1)? };
+                let reward = self.check_daily_cap(&creator, reward)?;
+                self.credit_mtoshi(&creator, reward)?;
+                self.debit_mtoshi(&player, cost)?;
+                self.platform_pool = platform_pool;
+                self.community_pool = community_pool;
+                self.burn_tokens(cost)?;
+                if let Some((_, _, likes, _, _, reputation_points)) = self.posts.get_mut(&post_id) {
+                    likes.push(player.clone());
+                    *reputation_points += 1;
                 }
+                let reputation_increase = if paid { 2 } else { 1 };
+                self.reputations
+                    .entry(player.clone())
+                    .and_modify(|e| *e += reputation_increase)
+                    .or_insert(reputation_increase);
+                println!("{} liked post {} (paid: {})", player, post_id, paid);
+                ActionResult::PostLiked { player, post_id, paid }
             }
             Action::CommentPost { player, post_id, comment } => {
-                if let Some((creator, _, _, _comments, _, _reputation_points)) = self.posts.get(&post_id) {
-                    let creator = creator.clone();
-                    let reward = self.check_daily_cap(&creator, 1 * 1_000_000_000);
-                    let post_id = post_id.clone();
-                    let player = player.clone();
-                    if let Some((_, _, _, comments, _, reputation_points)) = self.posts.get_mut(&post_id) {
-                        comments.push(comment.clone());
-                        *reputation_points += 1;
-                    }
-                    if let Some(creator_player) = self.full_gamefi_runtime.players.get_mut(&creator) {
-                        creator_player.mtoshi_balance += reward;
-                        self.burn_tokens(reward);
-                    }
-                    self.reputations
-                        .entry(player.clone())
-                        .and_modify(|e| *e += 1)
-                        .or_insert(1);
-                    println!("{} commented on post {}: {}", player, post_id, comment);
-                    ActionResult::PostCommented { player, post_id }
-                } else {
-                    ActionResult::Error { message: "Post not found".to_string() }
+                let (creator, _, _, _, _, _) = self.posts.get(&post_id).ok_or_else(|| GameError::not_found("Post", post_id.clone()))?;
+                let creator = creator.clone();
+                let reward = self.check_daily_cap(&creator, Amount::from_tokens(1)?)?;
+                self.credit_mtoshi(&creator, reward)?;
+                self.burn_tokens(reward)?;
+                if let Some((_, _, _, comments, _, reputation_points)) = self.posts.get_mut(&post_id) {
+                    comments.push(comment.clone());
+                    *reputation_points += 1;
                 }
+                self.reputations
+                    .entry(player.clone())
+                    .and_modify(|e| *e += 1)
+                    .or_insert(1);
+                println!("{} commented on post {}: {}", player, post_id, comment);
+                ActionResult::PostCommented { player, post_id }
             }
             Action::BoostPost { player, post_id, tokens } => {
-                let cost = tokens * 1_000_000_000; // tokens DTOSHI
-                if cost < 1_000_000_000 || cost > 2_000_000_000 {
-                    return ActionResult::Error { message: "Boost cost must be 1-2 DTOSHI".to_string() };
+                if !(1..=2).contains(&tokens) {
+                    return Err(GameError::InvalidAmount { reason: "Boost cost must be 1-2 DTOSHI".to_string() });
                 }
-                if self.full_gamefi_runtime.players.get(&player).map(|p| p.mtoshi_balance >= cost).unwrap_or(false) {
-                    if let Some((creator, _, _, _, _boosts, _reputation_points)) = self.posts.get(&post_id) {
-                        if creator != &player {
-                            return ActionResult::Error { message: "Only post creator can boost".to_string() };
-                        }
-                        let creator = creator.clone();
-                        let creator_share = (cost as f64 * 0.8) as u64;
-                        let platform_share = (cost as f64 * 0.1) as u64;
-                        let community_share = (cost as f64 * 0.1) as u64;
-                        let reward = self.check_daily_cap(&creator, creator_share);
-                        let post_id = post_id.clone();
-                        self.full_gamefi_runtime.players.get_mut(&player).unwrap().mtoshi_balance -= cost;
-                        if let Some((_, _, _, _, boosts, reputation_points)) = self.posts.get_mut(&post_id) {
-                            *boosts += tokens;
-                            *reputation_points += tokens;
-                        }
-                        if let Some(creator_player) = self.full_gamefi_runtime.players.get_mut(&creator) {
-                            creator_player.mtoshi_balance += reward;
-                        }
-                        self.platform_pool += platform_share;
-                        self.community_pool += community_share;
-                        self.burn_tokens(cost);
-                        println!("{} boosted post {} for {} DTOSHI", player, post_id, tokens);
-                        ActionResult::PostBoosted { player, post_id, tokens }
-                    } else {
-                        ActionResult::Error { message: "Post not found".to_string() }
-                    }
-                } else {
-                    ActionResult::Error { message: "Insufficient DTOSHI".to_string() }
+                let cost = Amount::from_tokens(tokens)?;
+                self.ledger().require(&player, MTOSHI, cost)?;
+                let (creator, _, _, _, _, _) = self.posts.get(&post_id).ok_or_else(|| GameError::not_found("Post", post_id.clone()))?;
+                if creator != &player {
+                    return Err(GameError::Unauthorized { reason: "Only post creator can boost".to_string() });
+                }
+                let platform_pool = self.platform_pool.checked_add(cost.percent(10)?)?;
+                let community_pool = self.community_pool.checked_add(cost.percent(10)?)?;
+                let reward = self.check_daily_cap(&player, cost.percent(80)?)?;
+                self.credit_mtoshi(&player, reward)?;
+                self.debit_mtoshi(&player, cost)?;
+                self.platform_pool = platform_pool;
+                self.community_pool = community_pool;
+                if let Some((_, _, _, _, boosts, reputation_points)) = self.posts.get_mut(&post_id) {
+                    *boosts += tokens;
+                    *reputation_points += tokens;
                 }
+                self.burn_tokens(cost)?;
+                println!("{} boosted post {} for {} DTOSHI", player, post_id, tokens);
+                ActionResult::PostBoosted { player, post_id, tokens }
             }
             Action::GetPosts => {
                 let posts = self.posts.iter()
@@ -518,45 +1358,48 @@ impl Blockchain {
                 let listings = self.nft_marketplace.iter()
                     .flat_map(|(seller, listings)| {
                         listings.iter().filter_map(|(index, price)| {
-                            self.full_gamefi_runtime.players.get(seller)
+                            self.runtime.players.get(seller)
                                 .and_then(|p| p.inventory.get(*index as usize))
-                                .map(|nft| (seller.clone(), *index, nft.name.clone(), *price))
+                                .map(|nft| (seller.clone(), *index, nft.name.clone(), price.units()))
                         })
                     })
                     .collect();
                 ActionResult::NftListings { listings }
             }
-        }
+            Action::PendingTransactions => {
+                ActionResult::PendingTransactions { transactions: self.mempool.pending() }
+            }
+            Action::DropTransaction { hash } => {
+                let dropped = self.mempool.remove(&hash).is_some();
+                ActionResult::TransactionDropped { hash, dropped }
+            }
+        };
+        Ok(result)
     }
 
     pub fn simulate_player_activity(&mut self) {
-        self.full_gamefi_runtime.simulate_player_activity();
+        self.runtime.simulate_player_activity();
     }
 
     pub fn show_leaderboard(&self) {
-        self.full_gamefi_runtime.show_leaderboard();
-    }
-
-    pub fn distribute_time_rewards(&mut self) {
-        let rewards: Vec<(String, String, u64)> = self.posts.iter()
-            .map(|(post_id, (creator, _, _, _, _, _))| {
-                let reward = self.get_mining_reward(creator);
-                (creator.clone(), post_id.clone(), reward)
-            })
-            .chain(self.content.iter()
-                .map(|(content_id, (creator, _, _, _, _))| {
-                    let reward = self.get_mining_reward(creator);
-                    (creator.clone(), content_id.clone(), reward)
-                }))
-            .collect();
+        self.runtime.show_leaderboard();
+    }
+
+    pub fn distribute_time_rewards(&mut self) -> Result<(), GameError> {
+        let rewards = self.posts.iter()
+            .map(|(post_id, (creator, _, _, _, _, _))| (creator, post_id))
+            .chain(self.content.iter().map(|(content_id, (creator, _, _, _, _))| (creator, content_id)))
+            .map(|(creator, id)| Ok((creator.clone(), id.clone(), self.get_mining_reward(creator)?)))
+            .collect::<Result<Vec<_>, GameError>>()?;
 
         for (creator, id, reward) in rewards {
-            let capped_reward = self.check_daily_cap(&creator, reward);
-            if let Some(creator_player) = self.full_gamefi_runtime.players.get_mut(&creator) {
-                creator_player.mtoshi_balance += capped_reward;
-                self.burn_tokens(capped_reward);
-                println!("Distributed {} DTOSHI to {} for {}", capped_reward / 1_000_000_000, creator, id);
+            let capped_reward = self.check_daily_cap(&creator, reward)?;
+            if self.runtime.players.contains_key(&creator) {
+                self.credit_mtoshi(&creator, capped_reward)?;
+                self.burn_tokens(capped_reward)?;
+                println!("Distributed {} DTOSHI to {} for {}", capped_reward, creator, id);
             }
         }
+        Ok(())
     }
-}
+}
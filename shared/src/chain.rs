@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::consensus::Consensus;
+use crate::merkle;
+use crate::runtime::{catena_node::Block, DEFAULT_DIFFICULTY};
+
+/// How far ahead of the local clock a block timestamp may be before it is rejected.
+pub const MAX_FUTURE_DRIFT_SECS: u64 = 120;
+/// Fixed genesis timestamp (2025-01-01T00:00:00Z) so every node derives the same genesis hash.
+pub const GENESIS_TIMESTAMP: u64 = 1_735_689_600;
+/// Number of blocks between difficulty retargets.
+pub const RETARGET_INTERVAL: u64 = 10;
+/// Block time the retarget steers towards.
+pub const TARGET_BLOCK_TIME_SECS: u64 = 10;
+/// A single retarget never moves difficulty by more than this factor, as in Bitcoin.
+pub const MAX_RETARGET_FACTOR: u64 = 4;
+/// Deepest reorg a node follows. Competing branches forking off further back
+/// are refused and kept fork blocks older than this are dropped.
+pub const MAX_REORG_DEPTH: u64 = 100;
+
+/// Linked history of mined blocks, starting from a shared genesis block. The
+/// main chain is the branch with the most cumulative work; valid blocks on
+/// competing branches are kept alongside it in case one of them overtakes it.
+#[derive(Debug, Clone)]
+pub struct Chain {
+    blocks: Vec<Block>,
+    by_hash: HashMap<String, u64>,
+    /// Sum of difficulties along the main chain up to each height.
+    cumulative_work: Vec<u128>,
+    forks: HashMap<String, Block>,
+    consensus: Consensus,
+    /// Height of the latest final block; nothing at or below it is ever reorganized.
+    finalized: u64,
+}
+
+impl Default for Chain {
+    fn default() -> Self {
+        Chain::new(DEFAULT_DIFFICULTY)
+    }
+}
+
+impl Chain {
+    pub fn new(initial_difficulty: u64) -> Self {
+        let genesis = Self::genesis_block(initial_difficulty);
+        let mut by_hash = HashMap::new();
+        by_hash.insert(genesis.hash.clone(), genesis.index);
+        Chain {
+            cumulative_work: vec![genesis.difficulty as u128],
+            blocks: vec![genesis],
+            by_hash,
+            forks: HashMap::new(),
+            consensus: Consensus::ProofOfWork,
+            finalized: 0,
+        }
+    }
+
+    /// A chain sealed by `consensus`. Proof-of-authority blocks all declare
+    /// difficulty 1, so the heaviest branch is the longest one.
+    pub fn with_consensus(consensus: Consensus) -> Self {
+        let initial_difficulty = match consensus {
+            Consensus::ProofOfWork => DEFAULT_DIFFICULTY,
+            Consensus::ProofOfAuthority(_) => 1,
+        };
+        Chain { consensus, ..Chain::new(initial_difficulty) }
+    }
+
+    pub fn consensus(&self) -> &Consensus {
+        &self.consensus
+    }
+
+    /// The genesis block commits to the root of an empty state; genesis
+    /// allocations are applied by every node on top of it.
+    pub fn genesis_block(initial_difficulty: u64) -> Block {
+        let mut genesis = Block::new(0, GENESIS_TIMESTAMP, "0".repeat(64), vec![], vec![], merkle::empty_root(), initial_difficulty.max(1));
+        genesis.hash = genesis.compute_hash();
+        genesis
+    }
+
+    /// Difficulty the next block on top of the head must declare. Every
+    /// `RETARGET_INTERVAL` blocks it is rescaled by how long the last window
+    /// actually took compared to `TARGET_BLOCK_TIME_SECS`, clamped to
+    /// `MAX_RETARGET_FACTOR` in either direction.
+    pub fn next_difficulty(&self) -> u64 {
+        self.difficulty_after(self.head())
+    }
+
+    /// Difficulty a child of `parent` must declare, on whichever branch it is.
+    fn difficulty_after(&self, parent: &Block) -> u64 {
+        let next_index = parent.index + 1;
+        if matches!(self.consensus, Consensus::ProofOfAuthority(_)) || !next_index.is_multiple_of(RETARGET_INTERVAL) {
+            return parent.difficulty;
+        }
+        let Some(window_start) = self.ancestor_at(&parent.hash, next_index - RETARGET_INTERVAL) else {
+            return parent.difficulty;
+        };
+        let expected = (RETARGET_INTERVAL - 1) * TARGET_BLOCK_TIME_SECS;
+        let actual = parent
+            .timestamp
+            .saturating_sub(window_start.timestamp)
+            .clamp(expected / MAX_RETARGET_FACTOR, expected * MAX_RETARGET_FACTOR)
+            .max(1);
+        let retargeted = (parent.difficulty as u128 * expected as u128) / actual as u128;
+        retargeted.clamp(1, u64::MAX as u128) as u64
+    }
+
+    pub fn height(&self) -> u64 {
+        self.head().index
+    }
+
+    pub fn head(&self) -> &Block {
+        self.blocks.last().expect("chain always holds the genesis block")
+    }
+
+    pub fn finalized_head(&self) -> &Block {
+        &self.blocks[self.finalized as usize]
+    }
+
+    /// Marks the main-chain block `hash`, and with it all its ancestors, final.
+    pub fn finalize(&mut self, hash: &str) -> Result<(), String> {
+        let height = *self.by_hash.get(hash).ok_or_else(|| format!("Block {} is not on the main chain", hash))?;
+        if height > self.finalized {
+            println!("Finalized block {} at height {}", hash, height);
+            self.finalized = height;
+            let finalized = self.finalized;
+            self.forks.retain(|_, fork| fork.index > finalized);
+        }
+        Ok(())
+    }
+
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    /// Parent hash and height the next block on top of the head must use.
+    pub fn next_position(&self) -> (String, u64) {
+        let head = self.head();
+        (head.hash.clone(), head.index + 1)
+    }
+
+    pub fn get_by_height(&self, height: u64) -> Option<&Block> {
+        self.blocks.get(height as usize)
+    }
+
+    /// Main-chain block with this hash.
+    pub fn get_by_hash(&self, hash: &str) -> Option<&Block> {
+        self.by_hash.get(hash).and_then(|height| self.get_by_height(*height))
+    }
+
+    /// Block with this hash on the main chain or on a competing branch.
+    pub fn get_block(&self, hash: &str) -> Option<&Block> {
+        self.get_by_hash(hash).or_else(|| self.forks.get(hash))
+    }
+
+    pub fn contains(&self, hash: &str) -> bool {
+        self.by_hash.contains_key(hash) || self.forks.contains_key(hash)
+    }
+
+    pub fn is_main(&self, hash: &str) -> bool {
+        self.by_hash.contains_key(hash)
+    }
+
+    /// Cumulative work of the main chain.
+    pub fn work(&self) -> u128 {
+        *self.cumulative_work.last().expect("chain always holds the genesis block")
+    }
+
+    /// Cumulative work of the branch ending in `hash`.
+    pub fn work_of(&self, hash: &str) -> Option<u128> {
+        let (fork_height, branch) = self.branch(hash)?;
+        Some(self.cumulative_work[fork_height as usize] + branch.iter().map(|block| block.difficulty as u128).sum::<u128>())
+    }
+
+    /// Height at which the branch ending in `tip` leaves the main chain, and
+    /// the branch's blocks after that point, oldest first. Empty for a
+    /// main-chain block; `None` if the branch does not connect.
+    pub fn branch(&self, tip: &str) -> Option<(u64, Vec<Block>)> {
+        let mut branch = vec![];
+        let mut hash = tip;
+        while !self.by_hash.contains_key(hash) {
+            let block = self.forks.get(hash)?;
+            branch.push(block.clone());
+            hash = &block.previous_hash;
+        }
+        branch.reverse();
+        Some((self.by_hash[hash], branch))
+    }
+
+    /// Ancestor at `height` of the block `hash`, following its own branch.
+    pub fn ancestor_at(&self, hash: &str, height: u64) -> Option<&Block> {
+        let mut block = self.get_block(hash)?;
+        while !self.by_hash.contains_key(&block.hash) {
+            if block.index <= height {
+                return (block.index == height).then_some(block);
+            }
+            block = self.get_block(&block.previous_hash)?;
+        }
+        if height <= block.index {
+            self.get_by_height(height)
+        } else {
+            None
+        }
+    }
+
+    pub fn validate_next(&self, block: &Block) -> Result<(), String> {
+        let head = self.head();
+        if block.index != head.index + 1 {
+            return Err(format!("Invalid block index: expected {}, got {}", head.index + 1, block.index));
+        }
+        if block.previous_hash != head.hash {
+            return Err(format!("Invalid parent for block {}: expected {}, got {}", block.index, head.hash, block.previous_hash));
+        }
+        if self.by_hash.contains_key(&block.hash) {
+            return Err(format!("Block {} already in chain", block.hash));
+        }
+        self.validate_child(block, head)
+    }
+
+    /// Header checks of `block` against its parent, wherever the parent is.
+    fn validate_child(&self, block: &Block, parent: &Block) -> Result<(), String> {
+        if block.index != parent.index + 1 {
+            return Err(format!("Invalid block index: expected {}, got {}", parent.index + 1, block.index));
+        }
+        if block.timestamp < parent.timestamp {
+            return Err(format!("Block {} timestamp {} is earlier than parent timestamp {}", block.index, block.timestamp, parent.timestamp));
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        if block.timestamp > now + MAX_FUTURE_DRIFT_SECS {
+            return Err(format!("Block {} timestamp {} is too far in the future", block.index, block.timestamp));
+        }
+        let expected_difficulty = self.difficulty_after(parent);
+        if block.difficulty != expected_difficulty {
+            return Err(format!("Invalid difficulty for block {}: expected {}, got {}", block.index, expected_difficulty, block.difficulty));
+        }
+        if let Consensus::ProofOfAuthority(validators) = &self.consensus {
+            validators.check_block(block, parent)?;
+        }
+        block.verify()
+    }
+
+    pub fn append(&mut self, block: Block) -> Result<(), String> {
+        self.validate_next(&block)?;
+        println!("Appended block {} to chain: {}", block.index, block.hash);
+        self.forks.remove(&block.hash);
+        self.cumulative_work.push(self.work() + block.difficulty as u128);
+        self.by_hash.insert(block.hash.clone(), block.index);
+        self.blocks.push(block);
+        let height = self.height();
+        self.forks.retain(|_, fork| fork.index + MAX_REORG_DEPTH > height);
+        Ok(())
+    }
+
+    /// Keeps a valid block that does not extend the head. Its state is only
+    /// checked if its branch ever becomes the main chain.
+    pub fn add_fork(&mut self, block: Block) -> Result<(), String> {
+        if self.contains(&block.hash) {
+            return Err(format!("Block {} already known", block.hash));
+        }
+        let parent = self
+            .get_block(&block.previous_hash)
+            .ok_or_else(|| format!("Unknown parent {} for block {}", block.previous_hash, block.index))?;
+        self.validate_child(&block, parent)?;
+        let (fork_height, _) = self.branch(&block.previous_hash).expect("parent is connected");
+        if fork_height < self.finalized {
+            return Err(format!("Block {} forks off below finalized height {}", block.hash, self.finalized));
+        }
+        if fork_height + MAX_REORG_DEPTH < self.height() {
+            return Err(format!("Block {} forks off at height {}, deeper than {} blocks", block.hash, fork_height, MAX_REORG_DEPTH));
+        }
+        println!("Stored block {} on a competing branch: {}", block.index, block.hash);
+        self.forks.insert(block.hash.clone(), block);
+        Ok(())
+    }
+
+    /// Removes main-chain blocks above `height`, keeping them as a competing
+    /// branch, and returns them oldest first.
+    pub fn disconnect_to(&mut self, height: u64) -> Vec<Block> {
+        assert!(height >= self.finalized, "final blocks are never disconnected");
+        let disconnected = self.blocks.split_off(height as usize + 1);
+        self.cumulative_work.truncate(height as usize + 1);
+        for block in &disconnected {
+            self.by_hash.remove(&block.hash);
+            self.forks.insert(block.hash.clone(), block.clone());
+        }
+        disconnected
+    }
+
+    /// Forgets a fork block and every fork block built on it.
+    pub fn remove_fork(&mut self, hash: &str) {
+        if self.forks.remove(hash).is_none() {
+            return;
+        }
+        let children: Vec<String> = self.forks.values().filter(|block| block.previous_hash == hash).map(|block| block.hash.clone()).collect();
+        for child in children {
+            self.remove_fork(&child);
+        }
+    }
+}
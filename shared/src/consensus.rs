@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::chain::GENESIS_TIMESTAMP;
+use crate::runtime::catena_node::{sha256_hex, Block};
+
+/// How blocks are sealed and who may produce them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub enum Consensus {
+    /// Anyone may mine a block by finding a nonce for the current difficulty.
+    #[default]
+    ProofOfWork,
+    /// A fixed set of validators take turns, one slot each, signing headers
+    /// with their libp2p identity.
+    ProofOfAuthority(ValidatorSet),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidatorSet {
+    /// Validator account ids (peer IDs) in turn order.
+    pub validators: Vec<String>,
+    pub slot_duration_secs: u64,
+}
+
+#[derive(Deserialize)]
+struct KeyFile {
+    peer_id: String,
+}
+
+impl ValidatorSet {
+    pub fn new(validators: Vec<String>, slot_duration_secs: u64) -> Self {
+        ValidatorSet { validators, slot_duration_secs: slot_duration_secs.max(1) }
+    }
+
+    /// Reads the `peer_id` of each key file written by the `utils`
+    /// `generate-keys` binary, in the order given.
+    pub fn from_key_files(paths: &[impl AsRef<Path>], slot_duration_secs: u64) -> io::Result<Self> {
+        let mut validators = vec![];
+        for path in paths {
+            let key_file: KeyFile = serde_json::from_slice(&fs::read(path)?)?;
+            validators.push(key_file.peer_id);
+        }
+        Ok(ValidatorSet::new(validators, slot_duration_secs))
+    }
+
+    /// Slot a timestamp falls into. Slot 0 starts at genesis.
+    pub fn slot_at(&self, timestamp: u64) -> u64 {
+        timestamp.saturating_sub(GENESIS_TIMESTAMP) / self.slot_duration_secs
+    }
+
+    /// Validator whose turn it is in `slot` when turns go round robin.
+    pub fn validator_for(&self, slot: u64) -> Option<&str> {
+        if self.validators.is_empty() {
+            return None;
+        }
+        Some(&self.validators[(slot % self.validators.len() as u64) as usize])
+    }
+
+    /// Validator whose turn it is in `slot`, drawn with probability
+    /// proportional to `weight_of` using the slot number as the seed, so every
+    /// node with the same stakes agrees. Validators weighted `None`, such as
+    /// jailed ones, are skipped. Round robin while no eligible validator has
+    /// stake.
+    pub fn scheduled_validator(&self, slot: u64, weight_of: impl Fn(&str) -> Option<u64>) -> Option<&str> {
+        let eligible: Vec<(&str, u128)> = self
+            .validators
+            .iter()
+            .filter_map(|validator| weight_of(validator).map(|weight| (validator.as_str(), weight as u128)))
+            .collect();
+        if eligible.is_empty() {
+            return None;
+        }
+        let total: u128 = eligible.iter().map(|(_, weight)| weight).sum();
+        if total == 0 {
+            return Some(eligible[(slot % eligible.len() as u64) as usize].0);
+        }
+        let seed = u64::from_str_radix(&sha256_hex(&slot.to_be_bytes())[..16], 16).expect("sha256 hex");
+        let mut point = seed as u128 % total;
+        for (validator, weight) in eligible {
+            if point < weight {
+                return Some(validator);
+            }
+            point -= weight;
+        }
+        unreachable!("point is below the total stake")
+    }
+
+    /// Checks that `block` was signed by a validator, that its timestamp
+    /// lies in its slot and that the slot comes after its parent's. Slots in
+    /// between may be empty when validators miss turns. Whether the slot was
+    /// the signer's turn depends on stake, which the block's importer checks
+    /// against the parent state.
+    pub fn check_block(&self, block: &Block, parent: &Block) -> Result<(), String> {
+        if block.slot <= parent.slot {
+            return Err(format!("Block {} is for slot {}, not after its parent's slot {}", block.index, block.slot, parent.slot));
+        }
+        if self.slot_at(block.timestamp) != block.slot {
+            return Err(format!("Block {} timestamp {} is outside slot {}", block.index, block.timestamp, block.slot));
+        }
+        let signer = block.verify_seal()?;
+        if !self.validators.contains(&signer) {
+            return Err(format!("Block {} is signed by {}, who is not a validator", block.index, signer));
+        }
+        Ok(())
+    }
+
+    /// Checks that two headers are both validly sealed by the same validator at
+    /// the same height but are different blocks, returning the validator.
+    /// Headers sealed by anyone outside the set are not evidence. Only header
+    /// fields are needed, so the blocks' bodies may be stripped.
+    pub fn verify_double_sign(&self, first: &Block, second: &Block) -> Result<String, String> {
+        if first.index != second.index {
+            return Err(format!("Headers are at different heights {} and {}", first.index, second.index));
+        }
+        if first.hash == second.hash {
+            return Err("Both headers are the same block".to_string());
+        }
+        for header in [first, second] {
+            if header.compute_hash() != header.hash {
+                return Err(format!("Header {} does not match its hash", header.hash));
+            }
+        }
+        let signer = first.verify_seal()?;
+        let other = second.verify_seal()?;
+        if signer != other {
+            return Err(format!("Headers are signed by different validators {} and {}", signer, other));
+        }
+        if !self.validators.contains(&signer) {
+            return Err(format!("Headers are signed by {}, who is not a validator", signer));
+        }
+        Ok(signer)
+    }
+}
@@ -0,0 +1,124 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use crate::auth::AuthError;
+use crate::mempool::MempoolError;
+use crate::staking::StakingError;
+
+/// Why an action failed. Each variant has a stable JSON-RPC error code, so
+/// clients can branch on `code` instead of parsing `message`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameError {
+    /// Unsigned or badly signed actions, and actions the signer may not perform.
+    Unauthorized { reason: String },
+    InvalidNonce { expected: u64, got: u64 },
+    /// Amounts are in the token's smallest unit.
+    InsufficientBalance { needed: u64, available: u64, token: String },
+    NotFound { kind: String, id: String },
+    AlreadyLiked { kind: String, id: String },
+    InvalidAmount { reason: String },
+    InvalidEvidence { reason: String },
+    /// The mempool refused to queue the action.
+    Rejected { reason: String },
+    /// The action is well formed but not possible in the node's current mode or state.
+    NotAllowed { reason: String },
+    /// Token arithmetic that would overflow or go below zero.
+    Overflow { operation: String },
+}
+
+impl GameError {
+    pub fn not_found(kind: &str, id: impl Into<String>) -> Self {
+        GameError::NotFound { kind: kind.to_string(), id: id.into() }
+    }
+
+    /// JSON-RPC error code. These are part of the API and must not change.
+    pub fn code(&self) -> i64 {
+        match self {
+            GameError::Unauthorized { .. } => 1001,
+            GameError::InvalidNonce { .. } => 1002,
+            GameError::InsufficientBalance { .. } => 1003,
+            GameError::NotFound { .. } => 1004,
+            GameError::AlreadyLiked { .. } => 1005,
+            GameError::InvalidAmount { .. } => 1006,
+            GameError::InvalidEvidence { .. } => 1007,
+            GameError::Rejected { .. } => 1008,
+            GameError::NotAllowed { .. } => 1009,
+            GameError::Overflow { .. } => 1010,
+        }
+    }
+
+    /// The JSON-RPC `error` object for this error, with the variant as `data`.
+    pub fn to_rpc_error(&self) -> RpcError {
+        RpcError { code: self.code(), message: self.to_string(), data: self.clone() }
+    }
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameError::Unauthorized { reason } => write!(f, "Unauthorized: {}", reason),
+            GameError::InvalidNonce { expected, got } => write!(f, "Invalid nonce: expected {}, got {}", expected, got),
+            GameError::InsufficientBalance { needed, available, token } => write!(f, "Insufficient {}: needed {}, available {}", token, needed, available),
+            GameError::NotFound { kind, id } => write!(f, "{} {} not found", kind, id),
+            GameError::AlreadyLiked { kind, id } => write!(f, "{} {} already liked", kind, id),
+            GameError::InvalidAmount { reason } => write!(f, "Invalid amount: {}", reason),
+            GameError::InvalidEvidence { reason } => write!(f, "Invalid evidence: {}", reason),
+            GameError::Rejected { reason } => write!(f, "Rejected: {}", reason),
+            GameError::NotAllowed { reason } => write!(f, "Not allowed: {}", reason),
+            GameError::Overflow { operation } => write!(f, "Arithmetic overflow in {}", operation),
+        }
+    }
+}
+
+impl std::error::Error for GameError {}
+
+impl From<AuthError> for GameError {
+    fn from(e: AuthError) -> Self {
+        match e {
+            AuthError::InvalidNonce { expected, got } => GameError::InvalidNonce { expected, got },
+            AuthError::InsufficientFee { needed, available } => GameError::InsufficientBalance { needed, available, token: "MTOSHI".to_string() },
+            AuthError::Unsigned
+            | AuthError::InvalidPublicKey
+            | AuthError::InvalidSignature
+            | AuthError::SignerMismatch { .. }
+            | AuthError::MissingCosignature { .. } => {
+                GameError::Unauthorized { reason: e.to_string() }
+            }
+        }
+    }
+}
+
+impl From<MempoolError> for GameError {
+    fn from(e: MempoolError) -> Self {
+        match e {
+            MempoolError::Auth(e) => e.into(),
+            MempoolError::StaleNonce { expected, got } => GameError::InvalidNonce { expected, got },
+            MempoolError::InsufficientFee { needed, available } => GameError::InsufficientBalance { needed, available, token: "MTOSHI".to_string() },
+            MempoolError::NotATransaction
+            | MempoolError::Duplicate
+            | MempoolError::SenderLimit { .. }
+            | MempoolError::Full
+            | MempoolError::RateLimited { .. } => {
+                GameError::Rejected { reason: e.to_string() }
+            }
+        }
+    }
+}
+
+impl From<StakingError> for GameError {
+    fn from(e: StakingError) -> Self {
+        match e {
+            StakingError::ZeroAmount => GameError::InvalidAmount { reason: e.to_string() },
+            StakingError::InsufficientStake { staked, requested } => {
+                GameError::InsufficientBalance { needed: requested.units(), available: staked.units(), token: "staked MTOSHI".to_string() }
+            }
+        }
+    }
+}
+
+/// A JSON-RPC 2.0 `error` member.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    pub data: GameError,
+}
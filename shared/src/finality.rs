@@ -0,0 +1,322 @@
+use libp2p::gossipsub::{self, IdentTopic};
+use libp2p::identity::{Keypair, PublicKey};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use crate::auth::account_id;
+
+/// Gossipsub topic validators exchange votes on.
+pub const FINALITY_TOPIC: &str = "catena-finality";
+
+pub fn finality_topic() -> IdentTopic {
+    IdentTopic::new(FINALITY_TOPIC)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum VoteKind {
+    Prevote,
+    Precommit,
+}
+
+/// Time the first round at a height may take before validators move on to
+/// the next one. Every further round waits this much longer again, so slow
+/// networks eventually get rounds long enough to agree in.
+pub const ROUND_TIMEOUT_SECS: u64 = 10;
+
+/// A validator's signed vote for the block `block_hash` at `height`, cast in
+/// `round`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Vote {
+    pub kind: VoteKind,
+    pub height: u64,
+    #[serde(default)]
+    pub round: u32,
+    pub block_hash: String,
+    /// Hex of the validator's protobuf-encoded public key.
+    pub validator: String,
+    /// Hex signature over `Vote::signing_bytes`.
+    pub signature: String,
+}
+
+impl Vote {
+    pub fn signing_bytes(kind: VoteKind, height: u64, round: u32, block_hash: &str) -> Vec<u8> {
+        serde_json::to_vec(&(kind, height, round, block_hash)).expect("vote serializes")
+    }
+
+    pub fn sign(keypair: &Keypair, kind: VoteKind, height: u64, round: u32, block_hash: &str) -> Self {
+        let signature = keypair
+            .sign(&Self::signing_bytes(kind, height, round, block_hash))
+            .expect("ed25519 signing does not fail");
+        Vote {
+            kind,
+            height,
+            round,
+            block_hash: block_hash.to_string(),
+            validator: hex::encode(keypair.public().encode_protobuf()),
+            signature: hex::encode(signature),
+        }
+    }
+
+    /// Checks the signature, returning the voter's account id.
+    pub fn verify(&self) -> Result<String, String> {
+        let public_key = hex::decode(&self.validator)
+            .ok()
+            .and_then(|bytes| PublicKey::try_decode_protobuf(&bytes).ok())
+            .ok_or("Vote has an invalid validator key")?;
+        let signature = hex::decode(&self.signature).map_err(|_| "Vote has a malformed signature")?;
+        if !public_key.verify(&Self::signing_bytes(self.kind, self.height, self.round, &self.block_hash), &signature) {
+            return Err("Vote has an invalid signature".to_string());
+        }
+        Ok(account_id(&public_key))
+    }
+}
+
+/// What a vote, block or timeout moved forward: votes this validator should
+/// gossip, and a block that just became final.
+#[derive(Debug, Default)]
+pub struct Progress {
+    pub votes: Vec<Vote>,
+    pub finalized: Option<(u64, String)>,
+}
+
+/// Round this node is in at an undecided height, and when the round started.
+/// The clock starts at the first `tick` after the height's first block.
+#[derive(Debug, Clone, Copy)]
+struct RoundState {
+    round: u32,
+    started: Option<u64>,
+}
+
+/// Tendermint-style finality among a fixed validator set. Voting at a height
+/// goes in rounds. Each validator prevotes the first block it sees at a
+/// height; once a block has prevotes from more than two thirds of the set in
+/// a round, validators that have the block precommit it in that round, and
+/// more than two thirds of precommits in any round make it final.
+///
+/// A round that does not decide times out after `ROUND_TIMEOUT_SECS` times
+/// its number plus one, and validators prevote again in the next round: for
+/// the block they are locked on if they precommitted one, otherwise for the
+/// known block with the most prevotes in the round before, ties going to the
+/// lowest hash, so split votes converge. A locked validator only precommits
+/// another block once that block gets a prevote quorum in a later round.
+/// A validator votes at most once per height, round and kind, and only the
+/// first such vote from each validator is counted.
+#[derive(Debug)]
+pub struct FinalityGadget {
+    keypair: Option<Keypair>,
+    validators: Vec<String>,
+    finalized: Option<(u64, String)>,
+    /// Blocks handed to `on_block`, by height.
+    known: HashMap<u64, BTreeSet<String>>,
+    votes: HashMap<(u64, u32, VoteKind, String), BTreeSet<String>>,
+    /// First vote counted from each validator, per height, round and kind.
+    counted: HashMap<(u64, u32, VoteKind, String), String>,
+    /// Rounds this node has already voted in, per height and kind.
+    cast: HashSet<(u64, u32, VoteKind)>,
+    rounds: BTreeMap<u64, RoundState>,
+    /// Block this node last precommitted at each height, and the round.
+    locked: HashMap<u64, (u32, String)>,
+}
+
+impl FinalityGadget {
+    /// `keypair` is this node's identity; it only votes if that identity is
+    /// in `validators`. Non-validators still follow votes to learn finality.
+    pub fn new(validators: Vec<String>, keypair: Option<Keypair>) -> Self {
+        FinalityGadget {
+            keypair,
+            validators,
+            finalized: None,
+            known: HashMap::new(),
+            votes: HashMap::new(),
+            counted: HashMap::new(),
+            cast: HashSet::new(),
+            rounds: BTreeMap::new(),
+            locked: HashMap::new(),
+        }
+    }
+
+    pub fn finalized(&self) -> Option<(u64, &str)> {
+        self.finalized.as_ref().map(|(height, hash)| (*height, hash.as_str()))
+    }
+
+    /// Round this node is voting in at `height`.
+    pub fn round(&self, height: u64) -> u32 {
+        self.rounds.get(&height).map(|state| state.round).unwrap_or(0)
+    }
+
+    fn is_validator(&self) -> bool {
+        let Some(keypair) = &self.keypair else {
+            return false;
+        };
+        let id = account_id(&keypair.public());
+        self.validators.contains(&id)
+    }
+
+    fn quorum(&self, count: usize) -> bool {
+        count * 3 > self.validators.len() * 2
+    }
+
+    fn below_finalized(&self, height: u64) -> bool {
+        self.finalized.as_ref().map(|(finalized, _)| height <= *finalized).unwrap_or(false)
+    }
+
+    /// Call with each block added to the chain; prevotes the first block at
+    /// its height in the current round, and precommits it if it already has
+    /// a prevote quorum.
+    pub fn on_block(&mut self, height: u64, block_hash: &str) -> Progress {
+        let mut progress = Progress::default();
+        if self.below_finalized(height) {
+            return progress;
+        }
+        self.known.entry(height).or_default().insert(block_hash.to_string());
+        let round = self.rounds.entry(height).or_insert(RoundState { round: 0, started: None }).round;
+        if !self.locked.contains_key(&height) {
+            self.cast_vote(VoteKind::Prevote, height, round, block_hash, &mut progress);
+        }
+        let polka_rounds: Vec<u32> = self
+            .votes
+            .iter()
+            .filter(|((h, _, kind, hash), voters)| *h == height && *kind == VoteKind::Prevote && hash == block_hash && self.quorum(voters.len()))
+            .map(|((_, round, _, _), _)| *round)
+            .collect();
+        for round in polka_rounds {
+            self.on_prevote_quorum(height, round, block_hash, &mut progress);
+        }
+        progress
+    }
+
+    /// Counts a vote received from the network.
+    pub fn on_vote(&mut self, vote: Vote) -> Result<Progress, String> {
+        let mut progress = Progress::default();
+        self.count_vote(vote, &mut progress)?;
+        Ok(progress)
+    }
+
+    /// Call regularly with the current time. Moves every undecided height
+    /// whose round has run out of time on to the next round and prevotes in
+    /// it.
+    pub fn tick(&mut self, now: u64) -> Progress {
+        let mut progress = Progress::default();
+        let heights: Vec<u64> = self.rounds.keys().copied().collect();
+        for height in heights {
+            let state = self.rounds.get_mut(&height).expect("height has a round");
+            let Some(started) = state.started else {
+                state.started = Some(now);
+                continue;
+            };
+            if now < started + ROUND_TIMEOUT_SECS * (state.round as u64 + 1) {
+                continue;
+            }
+            state.round += 1;
+            state.started = Some(now);
+            let round = state.round;
+            println!("Finality round {} at height {} timed out; starting round {}", round - 1, height, round);
+            let Some(choice) = self.prevote_choice(height, round) else {
+                continue;
+            };
+            self.cast_vote(VoteKind::Prevote, height, round, &choice, &mut progress);
+            if self.has_quorum(VoteKind::Prevote, height, round, &choice) {
+                self.on_prevote_quorum(height, round, &choice, &mut progress);
+            }
+        }
+        progress
+    }
+
+    /// Block to prevote at `height` in `round`: the locked block, otherwise
+    /// the known block with the most prevotes in the previous round.
+    fn prevote_choice(&self, height: u64, round: u32) -> Option<String> {
+        if let Some((_, hash)) = self.locked.get(&height) {
+            return Some(hash.clone());
+        }
+        let prevotes = |hash: &String| {
+            self.votes.get(&(height, round - 1, VoteKind::Prevote, hash.clone())).map(|voters| voters.len()).unwrap_or(0)
+        };
+        // `known` is ordered, and `max_by_key` keeps the last maximum.
+        self.known.get(&height)?.iter().rev().max_by_key(|hash| prevotes(hash)).cloned()
+    }
+
+    fn has_quorum(&self, kind: VoteKind, height: u64, round: u32, block_hash: &str) -> bool {
+        let count = self.votes.get(&(height, round, kind, block_hash.to_string())).map(|voters| voters.len()).unwrap_or(0);
+        self.quorum(count)
+    }
+
+    /// Precommits a block with a prevote quorum in `round` if this node has
+    /// it and is not locked on another block from that round or later.
+    fn on_prevote_quorum(&mut self, height: u64, round: u32, block_hash: &str, progress: &mut Progress) {
+        let have_block = self.known.get(&height).map(|hashes| hashes.contains(block_hash)).unwrap_or(false);
+        let unlocked = match self.locked.get(&height) {
+            Some((locked_round, locked_hash)) => locked_hash == block_hash || round > *locked_round,
+            None => true,
+        };
+        if have_block && unlocked && self.cast_vote(VoteKind::Precommit, height, round, block_hash, progress) {
+            self.locked.insert(height, (round, block_hash.to_string()));
+        }
+    }
+
+    /// Signs, counts and queues for gossip this node's vote, unless it is not
+    /// a validator or already voted that kind in that round. Returns whether
+    /// it voted.
+    fn cast_vote(&mut self, kind: VoteKind, height: u64, round: u32, block_hash: &str, progress: &mut Progress) -> bool {
+        if !self.is_validator() || !self.cast.insert((height, round, kind)) {
+            return false;
+        }
+        let vote = Vote::sign(self.keypair.as_ref().expect("validators have a keypair"), kind, height, round, block_hash);
+        progress.votes.push(vote.clone());
+        self.count_vote(vote, progress).expect("own vote is valid");
+        true
+    }
+
+    fn count_vote(&mut self, vote: Vote, progress: &mut Progress) -> Result<(), String> {
+        let voter = vote.verify()?;
+        if !self.validators.contains(&voter) {
+            return Err(format!("{} is not a validator", voter));
+        }
+        if self.below_finalized(vote.height) {
+            return Ok(());
+        }
+        let slot = (vote.height, vote.round, vote.kind, voter.clone());
+        match self.counted.get(&slot) {
+            Some(hash) if *hash != vote.block_hash => {
+                return Err(format!("{} already voted for {} at height {} round {}", voter, hash, vote.height, vote.round));
+            }
+            Some(_) => return Ok(()),
+            None => {
+                self.counted.insert(slot, vote.block_hash.clone());
+            }
+        }
+        let key = (vote.height, vote.round, vote.kind, vote.block_hash.clone());
+        self.votes.entry(key).or_default().insert(voter);
+        if !self.has_quorum(vote.kind, vote.height, vote.round, &vote.block_hash) {
+            return Ok(());
+        }
+        match vote.kind {
+            VoteKind::Prevote => self.on_prevote_quorum(vote.height, vote.round, &vote.block_hash, progress),
+            VoteKind::Precommit => {
+                println!("Block {} at height {} is final after round {}", vote.block_hash, vote.height, vote.round);
+                self.finalized = Some((vote.height, vote.block_hash.clone()));
+                progress.finalized = Some((vote.height, vote.block_hash));
+                self.prune();
+            }
+        }
+        Ok(())
+    }
+
+    fn prune(&mut self) {
+        let Some((finalized, _)) = self.finalized.clone() else {
+            return;
+        };
+        self.known.retain(|height, _| *height > finalized);
+        self.votes.retain(|(height, _, _, _), _| *height > finalized);
+        self.counted.retain(|(height, _, _, _), _| *height > finalized);
+        self.cast.retain(|(height, _, _)| *height > finalized);
+        self.rounds.retain(|height, _| *height > finalized);
+        self.locked.retain(|height, _| *height > finalized);
+    }
+}
+
+/// Decodes a vote from a gossipsub message on `FINALITY_TOPIC`.
+pub fn decode_vote(message: &gossipsub::Message) -> Option<Vote> {
+    if message.topic != finality_topic().hash() {
+        return None;
+    }
+    serde_json::from_slice(&message.data).ok()
+}
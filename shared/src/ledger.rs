@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use crate::amount::{Amount, DECIMALS};
+use crate::error::GameError;
+
+pub const SOLUTIO: &str = "Solutio";
+pub const MTOSHI: &str = "MTOSHI";
+
+/// A token the ledger can hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token {
+    pub id: &'static str,
+    pub decimals: u32,
+}
+
+/// Every known token. Balances in any other token id are refused. DTOSHI is
+/// not a token of its own but how whole MTOSHI are shown, so prices quoted
+/// in DTOSHI are paid in MTOSHI.
+pub const TOKENS: [Token; 2] = [
+    // Block and staking rewards.
+    Token { id: SOLUTIO, decimals: DECIMALS },
+    // Fees, stakes, likes, boosts and NFT trades.
+    Token { id: MTOSHI, decimals: DECIMALS },
+];
+
+pub fn token(id: &str) -> Result<&'static Token, GameError> {
+    TOKENS.iter().find(|token| token.id == id).ok_or_else(|| GameError::not_found("Token", id))
+}
+
+/// Balances of every account in every token, keyed by (account, token id).
+/// All token movements go through here, so each balance has one home.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Ledger {
+    balances: BTreeMap<String, BTreeMap<String, Amount>>, // account -> token id -> balance
+}
+
+impl Ledger {
+    pub fn balance(&self, account: &str, token: &str) -> Amount {
+        self.balances.get(account).and_then(|tokens| tokens.get(token)).copied().unwrap_or_default()
+    }
+
+    /// Every token the account has held, by token id. `None` for accounts
+    /// that never held any.
+    pub fn balances(&self, account: &str) -> Option<&BTreeMap<String, Amount>> {
+        self.balances.get(account)
+    }
+
+    pub fn accounts(&self) -> impl Iterator<Item = (&String, &BTreeMap<String, Amount>)> {
+        self.balances.iter()
+    }
+
+    /// Fails unless `account` holds at least `amount` of `token`.
+    pub fn require(&self, account: &str, token: &str, amount: Amount) -> Result<(), GameError> {
+        let available = self.balance(account, token);
+        if available < amount {
+            return Err(GameError::InsufficientBalance { needed: amount.units(), available: available.units(), token: token.to_string() });
+        }
+        Ok(())
+    }
+
+    /// Adds `amount` of `token` to the account, returning the new balance.
+    pub fn credit(&mut self, account: &str, token: &str, amount: Amount) -> Result<Amount, GameError> {
+        let token = self::token(token)?;
+        let balance = self.balance(account, token.id).checked_add(amount)?;
+        if !amount.is_zero() {
+            self.balances.entry(account.to_string()).or_default().insert(token.id.to_string(), balance);
+        }
+        Ok(balance)
+    }
+
+    /// Takes `amount` of `token` from the account, returning the new balance.
+    /// The balance is left untouched if it cannot cover `amount`.
+    pub fn debit(&mut self, account: &str, token: &str, amount: Amount) -> Result<Amount, GameError> {
+        self.require(account, token, amount)?;
+        let balance = self.balance(account, token).checked_sub(amount)?;
+        if !amount.is_zero() {
+            self.balances.entry(account.to_string()).or_default().insert(token.to_string(), balance);
+        }
+        Ok(balance)
+    }
+}
@@ -1,3 +1,17 @@
+pub mod amount;
+pub mod auth;
 pub mod blockchain;
+pub mod chain;
+pub mod consensus;
+pub mod error;
+pub mod finality;
+pub mod ledger;
+pub mod mempool;
+pub mod merkle;
 pub mod network;
-pub mod runtime;
+pub mod rng;
+pub mod runtime;
+pub mod staking;
+pub mod state;
+pub mod storage;
+pub mod sync;
@@ -0,0 +1,253 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+use std::fmt;
+use crate::auth::{AuthError, SignedAction};
+use crate::blockchain::Action;
+
+pub const DEFAULT_MAX_SIZE: usize = 5_000;
+pub const DEFAULT_MAX_PER_SENDER: usize = 64;
+/// Entries older than this are evicted even if their nonce never became current.
+pub const DEFAULT_MAX_AGE_SECS: u64 = 600;
+/// Most actions a block producer takes from the pool for one block.
+pub const MAX_BLOCK_ACTIONS: usize = 500;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MempoolError {
+    Auth(AuthError),
+    NotATransaction,
+    StaleNonce { expected: u64, got: u64 },
+    InsufficientFee { needed: u64, available: u64 },
+    Duplicate,
+    SenderLimit { sender: String, limit: usize },
+    Full,
+    /// The sender has to wait until `release_height` before repeating the action.
+    RateLimited { release_height: u64 },
+}
+
+impl fmt::Display for MempoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MempoolError::Auth(e) => write!(f, "{}", e),
+            MempoolError::NotATransaction => write!(f, "Queries and block production are not pooled"),
+            MempoolError::StaleNonce { expected, got } => write!(f, "Stale nonce: account is at {}, got {}", expected, got),
+            MempoolError::InsufficientFee { needed, available } => write!(f, "Pending fees of {} exceed MTOSHI balance of {}", needed, available),
+            MempoolError::Duplicate => write!(f, "Action already pending with an equal or higher fee"),
+            MempoolError::SenderLimit { sender, limit } => write!(f, "Sender {} already has {} pending actions", sender, limit),
+            MempoolError::Full => write!(f, "Mempool is full"),
+            MempoolError::RateLimited { release_height } => write!(f, "Rate limited until block {}", release_height),
+        }
+    }
+}
+
+impl std::error::Error for MempoolError {}
+
+impl From<AuthError> for MempoolError {
+    fn from(e: AuthError) -> Self {
+        MempoolError::Auth(e)
+    }
+}
+
+/// What the mempool needs to know about a sender from current state.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AccountState {
+    pub nonce: u64,
+    pub balance: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingEntry {
+    pub hash: String,
+    pub sender: String,
+    pub received_at: u64,
+    pub signed: SignedAction,
+}
+
+/// Signed actions that passed signature and nonce checks against the current
+/// state but have not been applied yet. Block producers draw from it by fee,
+/// keeping each sender's actions in nonce order.
+#[derive(Debug)]
+pub struct Mempool {
+    by_sender: HashMap<String, BTreeMap<u64, PendingEntry>>,
+    by_hash: HashMap<String, (String, u64)>,
+    max_size: usize,
+    max_per_sender: usize,
+    max_age_secs: u64,
+}
+
+impl Default for Mempool {
+    fn default() -> Self {
+        Mempool::new(DEFAULT_MAX_SIZE, DEFAULT_MAX_PER_SENDER, DEFAULT_MAX_AGE_SECS)
+    }
+}
+
+impl Mempool {
+    pub fn new(max_size: usize, max_per_sender: usize, max_age_secs: u64) -> Self {
+        Mempool {
+            by_sender: HashMap::new(),
+            by_hash: HashMap::new(),
+            max_size,
+            max_per_sender,
+            max_age_secs,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_hash.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_hash.is_empty()
+    }
+
+    pub fn contains(&self, hash: &str) -> bool {
+        self.by_hash.contains_key(hash)
+    }
+
+    /// Validates `signed` and queues it. `account` gives each sender's current
+    /// nonce and MTOSHI balance in state; actions at or above that nonce are
+    /// accepted as long as the balance covers the fees of everything the
+    /// sender has queued, so a sender may queue several in a row. Returns the
+    /// action's hash.
+    pub fn insert(&mut self, signed: SignedAction, account: impl Fn(&str) -> AccountState, now: u64) -> Result<String, MempoolError> {
+        if signed.action.is_query() || signed.action.is_local() || matches!(signed.action, Action::MineBlock { .. }) {
+            return Err(MempoolError::NotATransaction);
+        }
+        let sender = signed.verify()?;
+        let AccountState { nonce: expected, balance } = account(&sender);
+        if signed.nonce < expected {
+            return Err(MempoolError::StaleNonce { expected, got: signed.nonce });
+        }
+        let queued_fees: u64 = self
+            .by_sender
+            .get(&sender)
+            .map(|entries| {
+                entries
+                    .values()
+                    .filter(|entry| entry.signed.nonce != signed.nonce)
+                    .fold(0u64, |total, entry| total.saturating_add(entry.signed.fee()))
+            })
+            .unwrap_or(0);
+        let needed = queued_fees.saturating_add(signed.fee());
+        if needed > balance {
+            return Err(MempoolError::InsufficientFee { needed, available: balance });
+        }
+
+        let hash = signed.hash();
+        let queued = self.by_sender.get(&sender).map(|entries| entries.len()).unwrap_or(0);
+        let replaces = self
+            .by_sender
+            .get(&sender)
+            .and_then(|entries| entries.get(&signed.nonce))
+            .map(|existing| (existing.hash.clone(), existing.signed.fee()));
+        if let Some((existing, existing_fee)) = replaces {
+            if existing == hash || existing_fee >= signed.fee() {
+                return Err(MempoolError::Duplicate);
+            }
+            println!("Replacing pending action {} with higher-fee {}", existing, hash);
+            self.remove(&existing);
+        } else {
+            if queued >= self.max_per_sender {
+                return Err(MempoolError::SenderLimit { sender, limit: self.max_per_sender });
+            }
+            if self.len() >= self.max_size && !self.evict_cheapest(signed.fee()) {
+                return Err(MempoolError::Full);
+            }
+        }
+
+        println!("Queued action {} from {} (nonce {}, fee {})", hash, sender, signed.nonce, signed.fee());
+        self.by_hash.insert(hash.clone(), (sender.clone(), signed.nonce));
+        self.by_sender.entry(sender.clone()).or_default().insert(signed.nonce, PendingEntry {
+            hash: hash.clone(),
+            sender,
+            received_at: now,
+            signed,
+        });
+        Ok(hash)
+    }
+
+    /// Drops the lowest-fee entry that is last in its sender's queue, so no
+    /// sender is left with a nonce gap, provided it pays less than `fee`.
+    fn evict_cheapest(&mut self, fee: u64) -> bool {
+        let cheapest = self
+            .by_sender
+            .values()
+            .filter_map(|entries| entries.values().next_back())
+            .min_by_key(|entry| (entry.signed.fee(), Reverse(entry.received_at)))
+            .map(|entry| (entry.hash.clone(), entry.signed.fee()));
+        match cheapest {
+            Some((hash, cheapest_fee)) if cheapest_fee < fee => {
+                println!("Mempool full, evicting {} (fee {})", hash, cheapest_fee);
+                self.remove(&hash);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn remove(&mut self, hash: &str) -> Option<SignedAction> {
+        let (sender, nonce) = self.by_hash.remove(hash)?;
+        let entries = self.by_sender.get_mut(&sender)?;
+        let entry = entries.remove(&nonce);
+        if entries.is_empty() {
+            self.by_sender.remove(&sender);
+        }
+        entry.map(|entry| entry.signed)
+    }
+
+    /// All pending entries, grouped by sender in nonce order.
+    pub fn pending(&self) -> Vec<PendingEntry> {
+        let mut senders: Vec<&String> = self.by_sender.keys().collect();
+        senders.sort();
+        senders
+            .into_iter()
+            .flat_map(|sender| self.by_sender[sender].values().cloned())
+            .collect()
+    }
+
+    /// Picks up to `max` actions for a block: the highest-fee action whose
+    /// nonce is next for its sender goes first, and a sender's following
+    /// nonce becomes eligible once the previous one is taken.
+    pub fn select(&self, account_nonce: impl Fn(&str) -> u64, max: usize) -> Vec<SignedAction> {
+        let mut ready = BinaryHeap::new();
+        for (sender, entries) in &self.by_sender {
+            let nonce = account_nonce(sender);
+            if let Some(entry) = entries.get(&nonce) {
+                ready.push((entry.signed.fee(), Reverse(entry.received_at), Reverse(sender.clone()), nonce));
+            }
+        }
+
+        let mut selected = vec![];
+        while let Some((_, _, Reverse(sender), nonce)) = ready.pop() {
+            if selected.len() >= max {
+                break;
+            }
+            let entries = &self.by_sender[&sender];
+            selected.push(entries[&nonce].signed.clone());
+            if let Some(next) = entries.get(&(nonce + 1)) {
+                ready.push((next.signed.fee(), Reverse(next.received_at), Reverse(sender), nonce + 1));
+            }
+        }
+        selected
+    }
+
+    /// Evicts entries whose nonce has already been used and entries older
+    /// than the configured maximum age.
+    pub fn prune(&mut self, account_nonce: impl Fn(&str) -> u64, now: u64) {
+        let max_age_secs = self.max_age_secs;
+        let stale: Vec<String> = self
+            .by_sender
+            .iter()
+            .flat_map(|(sender, entries)| {
+                let current = account_nonce(sender);
+                entries
+                    .values()
+                    .filter(move |entry| entry.signed.nonce < current || now.saturating_sub(entry.received_at) > max_age_secs)
+                    .map(|entry| entry.hash.clone())
+            })
+            .collect();
+        for hash in stale {
+            self.remove(&hash);
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+// Leaves and inner nodes are hashed under different prefixes so an inner node
+// can never be passed off as a leaf (second-preimage attack).
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+fn hash_leaf(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+fn hash_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Builds every level of the tree, leaves first. An odd node at the end of a
+/// level is carried up unchanged rather than duplicated.
+fn build_levels(leaves: &[Vec<u8>]) -> Vec<Vec<[u8; 32]>> {
+    let mut levels = vec![leaves.iter().map(|leaf| hash_leaf(leaf)).collect::<Vec<_>>()];
+    while levels.last().map(|level| level.len() > 1).unwrap_or(false) {
+        let next = levels
+            .last()
+            .unwrap()
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => hash_node(left, right),
+                [single] => *single,
+                _ => unreachable!(),
+            })
+            .collect();
+        levels.push(next);
+    }
+    levels
+}
+
+/// Root of an empty tree.
+pub fn empty_root() -> String {
+    hex::encode(Sha256::digest([]))
+}
+
+pub fn merkle_root(leaves: &[Vec<u8>]) -> String {
+    if leaves.is_empty() {
+        return empty_root();
+    }
+    let levels = build_levels(leaves);
+    hex::encode(levels.last().unwrap()[0])
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// One sibling on the path from a leaf to the root, and which side it sits on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofStep {
+    pub hash: String,
+    pub side: Side,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleProof {
+    pub leaf_index: usize,
+    pub steps: Vec<ProofStep>,
+}
+
+/// Inclusion proof for `leaves[index]`, or `None` if the index is out of range.
+pub fn build_proof(leaves: &[Vec<u8>], index: usize) -> Option<MerkleProof> {
+    if index >= leaves.len() {
+        return None;
+    }
+    let levels = build_levels(leaves);
+    let mut steps = vec![];
+    let mut position = index;
+    for level in &levels[..levels.len() - 1] {
+        let sibling = position ^ 1;
+        if sibling < level.len() {
+            let side = if sibling < position { Side::Left } else { Side::Right };
+            steps.push(ProofStep { hash: hex::encode(level[sibling]), side });
+        }
+        position /= 2;
+    }
+    Some(MerkleProof { leaf_index: index, steps })
+}
+
+pub fn verify_proof(root: &str, leaf: &[u8], proof: &MerkleProof) -> bool {
+    let mut current = hash_leaf(leaf);
+    for step in &proof.steps {
+        let sibling: [u8; 32] = match hex::decode(&step.hash).ok().and_then(|bytes| bytes.try_into().ok()) {
+            Some(sibling) => sibling,
+            None => return false,
+        };
+        current = match step.side {
+            Side::Left => hash_node(&sibling, &current),
+            Side::Right => hash_node(&current, &sibling),
+        };
+    }
+    hex::encode(current) == root
+}
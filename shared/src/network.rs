@@ -1,18 +1,37 @@
 use libp2p::{
     gossipsub::{self, MessageAuthenticity, IdentTopic},
+    identify,
+    kad::{self, store::MemoryStore},
+    mdns,
     ping::Behaviour as PingBehaviour,
-    Swarm, SwarmBuilder, Multiaddr, PeerId, identity,
+    request_response::{self, ProtocolSupport, ResponseChannel},
+    Swarm, SwarmBuilder, Multiaddr, PeerId, StreamProtocol, identity,
     core::transport::Transport,
-    swarm::NetworkBehaviour,
+    futures::StreamExt,
+    multiaddr::Protocol,
+    swarm::{behaviour::toggle::Toggle, dial_opts::DialOpts, ConnectionId, DialError, NetworkBehaviour, SwarmEvent},
 };
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::auth::SignedAction;
+use crate::blockchain::Blockchain;
+use crate::chain::{Chain, MAX_REORG_DEPTH};
+use crate::finality::{decode_vote, finality_topic, FinalityGadget, Progress, Vote};
+use crate::runtime::catena_node::Block;
+use crate::sync::{self, ChainStatus, SyncRequest, SyncResponse, MAX_BLOCKS, MAX_HEADERS, SYNC_PROTOCOL};
 
 #[derive(Debug)]
 pub enum CustomEvent {
     Gossipsub(gossipsub::Event),
     Ping(libp2p::ping::Event),
+    Kademlia(kad::Event),
+    Identify(identify::Event),
+    Mdns(mdns::Event),
+    Sync(request_response::Event<SyncRequest, SyncResponse>),
 }
 
 impl From<gossipsub::Event> for CustomEvent {
@@ -27,11 +46,57 @@ impl From<libp2p::ping::Event> for CustomEvent {
     }
 }
 
+impl From<kad::Event> for CustomEvent {
+    fn from(event: kad::Event) -> Self {
+        CustomEvent::Kademlia(event)
+    }
+}
+
+impl From<identify::Event> for CustomEvent {
+    fn from(event: identify::Event) -> Self {
+        CustomEvent::Identify(event)
+    }
+}
+
+impl From<mdns::Event> for CustomEvent {
+    fn from(event: mdns::Event) -> Self {
+        CustomEvent::Mdns(event)
+    }
+}
+
+impl From<request_response::Event<SyncRequest, SyncResponse>> for CustomEvent {
+    fn from(event: request_response::Event<SyncRequest, SyncResponse>) -> Self {
+        CustomEvent::Sync(event)
+    }
+}
+
+/// Protocol version nodes announce over identify.
+pub const IDENTIFY_PROTOCOL: &str = "/catena/1.0.0";
+
+/// Gossipsub topic new blocks are announced on.
+pub const BLOCKS_TOPIC: &str = "catena-blocks";
+/// Gossipsub topic signed actions are relayed on before they are mined.
+pub const ACTIONS_TOPIC: &str = "catena-actions";
+
+pub fn blocks_topic() -> IdentTopic {
+    IdentTopic::new(BLOCKS_TOPIC)
+}
+
+pub fn actions_topic() -> IdentTopic {
+    IdentTopic::new(ACTIONS_TOPIC)
+}
+
 #[derive(NetworkBehaviour)]
 #[behaviour(to_swarm = "CustomEvent")]
 pub struct CombinedBehaviour {
     gossipsub: gossipsub::Behaviour,
     ping: PingBehaviour,
+    kademlia: kad::Behaviour<MemoryStore>,
+    identify: identify::Behaviour,
+    /// Local-network discovery, only enabled by `NetworkConfig::mdns`.
+    mdns: Toggle<mdns::tokio::Behaviour>,
+    /// Block sync, see `crate::sync`.
+    sync: request_response::json::Behaviour<SyncRequest, SyncResponse>,
 }
 
 impl CombinedBehaviour {
@@ -61,22 +126,538 @@ impl CombinedBehaviour {
     pub fn mesh_peers(&self, topic: &IdentTopic) -> impl Iterator<Item = &PeerId> {
         self.gossipsub.mesh_peers(&topic.hash())
     }
+
+    /// Adds a peer's address to the Kademlia routing table.
+    pub fn add_peer_address(&mut self, peer_id: &PeerId, addr: Multiaddr) {
+        self.kademlia.add_address(peer_id, addr);
+    }
+
+    pub fn remove_peer_address(&mut self, peer_id: &PeerId, addr: &Multiaddr) {
+        self.kademlia.remove_address(peer_id, addr);
+    }
+
+    pub fn mdns_enabled(&self) -> bool {
+        self.mdns.is_enabled()
+    }
+
+    /// Starts a DHT lookup of our own ID, which fills the routing table with
+    /// the peers closest to us and announces us to them.
+    pub fn bootstrap_dht(&mut self) {
+        if let Err(e) = self.kademlia.bootstrap() {
+            println!("Kademlia bootstrap skipped: {}", e);
+        }
+    }
+
+    pub fn send_sync_request(&mut self, peer_id: &PeerId, request: SyncRequest) -> request_response::OutboundRequestId {
+        self.sync.send_request(peer_id, request)
+    }
+
+    /// Answers a peer's sync request. Fails if the peer is gone or gave up
+    /// waiting.
+    pub fn send_sync_response(&mut self, channel: ResponseChannel<SyncResponse>, response: SyncResponse) -> Result<(), SyncResponse> {
+        self.sync.send_response(channel, response)
+    }
+
+    /// Every peer in the Kademlia routing table, with its known addresses.
+    pub fn routing_table(&mut self) -> Vec<(PeerId, Vec<Multiaddr>)> {
+        let mut peers = vec![];
+        for bucket in self.kademlia.kbuckets() {
+            for entry in bucket.iter() {
+                peers.push((*entry.node.key.preimage(), entry.node.value.iter().cloned().collect()));
+            }
+        }
+        peers
+    }
+}
+
+/// A routing-table entry, as shown by `getPeers`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerInfo {
+    pub peer_id: String,
+    pub addresses: Vec<String>,
+    pub connected: bool,
+}
+
+/// Delay before the first redial of a bootstrap peer; it doubles with every
+/// further failure up to `MAX_REDIAL_DELAY`.
+pub const BASE_REDIAL_DELAY: Duration = Duration::from_secs(1);
+pub const MAX_REDIAL_DELAY: Duration = Duration::from_secs(60);
+
+/// How long `Network::catch_up` waits for a first peer to sync from.
+pub const SYNC_PEER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where a bootstrap peer's connection stands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    Dialing,
+    Connected,
+    /// Not connected; redialed at `retry_at`.
+    Backoff { retry_at: Instant },
+}
+
+#[derive(Debug, Clone)]
+pub struct BootstrapPeer {
+    pub addr: Multiaddr,
+    /// Taken from a `/p2p/` suffix of the address, or learned on connecting.
+    pub peer_id: Option<PeerId>,
+    pub state: ConnectionState,
+    /// Failed dials and dropped connections since the last successful connection.
+    pub failures: u32,
+    dial: Option<ConnectionId>,
+}
+
+/// The configured `dial_addrs` and their connection state. Peers that cannot
+/// be reached, or whose connection drops, are redialed with exponential
+/// backoff for as long as the node runs.
+#[derive(Debug, Clone, Default)]
+pub struct BootstrapPeers {
+    peers: Vec<BootstrapPeer>,
+}
+
+impl BootstrapPeers {
+    /// Every peer starts out due for a dial at `now`.
+    pub fn new(addrs: Vec<Multiaddr>, now: Instant) -> Self {
+        let peers = addrs
+            .into_iter()
+            .map(|addr| BootstrapPeer {
+                peer_id: addr.iter().find_map(|protocol| match protocol {
+                    Protocol::P2p(peer_id) => Some(peer_id),
+                    _ => None,
+                }),
+                addr,
+                state: ConnectionState::Backoff { retry_at: now },
+                failures: 0,
+                dial: None,
+            })
+            .collect();
+        BootstrapPeers { peers }
+    }
+
+    pub fn peers(&self) -> &[BootstrapPeer] {
+        &self.peers
+    }
+
+    /// Delay before redialing after `failures` failures in a row.
+    pub fn redial_delay(failures: u32) -> Duration {
+        let factor = 2u32.saturating_pow(failures.saturating_sub(1));
+        BASE_REDIAL_DELAY.saturating_mul(factor).min(MAX_REDIAL_DELAY)
+    }
+
+    /// Indices of the peers whose backoff has run out by `now`.
+    pub fn due(&self, now: Instant) -> Vec<usize> {
+        (0..self.peers.len())
+            .filter(|&i| matches!(self.peers[i].state, ConnectionState::Backoff { retry_at } if retry_at <= now))
+            .collect()
+    }
+
+    /// When the next redial is due, if any peer is waiting for one.
+    pub fn next_retry(&self) -> Option<Instant> {
+        self.peers
+            .iter()
+            .filter_map(|peer| match peer.state {
+                ConnectionState::Backoff { retry_at } => Some(retry_at),
+                _ => None,
+            })
+            .min()
+    }
+
+    pub fn dialing(&mut self, index: usize, connection_id: ConnectionId) {
+        let peer = &mut self.peers[index];
+        peer.state = ConnectionState::Dialing;
+        peer.dial = Some(connection_id);
+    }
+
+    /// A connection to `peer_id` was established, either by our dial or by
+    /// the peer dialing us. Returns whether it is a bootstrap peer.
+    pub fn connected(&mut self, connection_id: ConnectionId, peer_id: PeerId) -> bool {
+        let mut matched = false;
+        for peer in &mut self.peers {
+            if peer.dial == Some(connection_id) || peer.peer_id == Some(peer_id) {
+                peer.peer_id = Some(peer_id);
+                peer.state = ConnectionState::Connected;
+                peer.failures = 0;
+                peer.dial = None;
+                matched = true;
+            }
+        }
+        matched
+    }
+
+    /// Whether `connection_id` is one of our dials to a bootstrap peer.
+    pub fn is_dial(&self, connection_id: ConnectionId) -> bool {
+        self.peers.iter().any(|peer| peer.dial == Some(connection_id))
+    }
+
+    pub fn dial_failed(&mut self, connection_id: ConnectionId, now: Instant) {
+        for peer in &mut self.peers {
+            if peer.dial == Some(connection_id) && peer.state == ConnectionState::Dialing {
+                Self::back_off(peer, now);
+            }
+        }
+    }
+
+    /// The last connection to `peer_id` closed.
+    pub fn disconnected(&mut self, peer_id: PeerId, now: Instant) {
+        for peer in &mut self.peers {
+            if peer.peer_id == Some(peer_id) && peer.state == ConnectionState::Connected {
+                Self::back_off(peer, now);
+            }
+        }
+    }
+
+    fn back_off(peer: &mut BootstrapPeer, now: Instant) {
+        peer.failures = peer.failures.saturating_add(1);
+        peer.dial = None;
+        peer.state = ConnectionState::Backoff { retry_at: now + Self::redial_delay(peer.failures) };
+    }
 }
 
 pub struct Network {
     pub swarm: Swarm<CombinedBehaviour>,
+    bootstrap: BootstrapPeers,
 }
 
 impl Network {
+    /// Connection state of every configured bootstrap peer.
+    pub fn bootstrap_peers(&self) -> &[BootstrapPeer] {
+        self.bootstrap.peers()
+    }
+
+    pub fn connected_peers(&self) -> Vec<PeerId> {
+        self.swarm.connected_peers().copied().collect()
+    }
+
+    pub fn is_connected(&self, peer_id: &PeerId) -> bool {
+        self.swarm.is_connected(peer_id)
+    }
+
+    /// The `getPeers` view: the Kademlia routing table, sorted by peer ID.
+    pub fn get_peers(&mut self) -> Vec<PeerInfo> {
+        let mut peers: Vec<PeerInfo> = self.swarm.behaviour_mut().routing_table()
+            .into_iter()
+            .map(|(peer_id, addresses)| PeerInfo {
+                peer_id: peer_id.to_string(),
+                addresses: addresses.iter().map(Multiaddr::to_string).collect(),
+                connected: self.swarm.is_connected(&peer_id),
+            })
+            .collect();
+        peers.sort_by(|a, b| a.peer_id.cmp(&b.peer_id));
+        peers
+    }
+
+    /// Waits for the next swarm event, redialing bootstrap peers as their
+    /// backoff runs out and keeping their connection state up to date.
+    pub async fn next_event(&mut self) -> SwarmEvent<CustomEvent> {
+        loop {
+            let retry_at = self.bootstrap.next_retry();
+            let redial = async move {
+                match retry_at {
+                    Some(retry_at) => tokio::time::sleep_until(retry_at.into()).await,
+                    None => std::future::pending().await,
+                }
+            };
+            tokio::select! {
+                event = self.swarm.select_next_some() => {
+                    self.handle_event(&event);
+                    return event;
+                }
+                _ = redial => self.redial_due(),
+            }
+        }
+    }
+
+    /// Updates bootstrap peers' connection state and the routing table from a
+    /// swarm event, and connects to peers the DHT turns up. Called by
+    /// `next_event`; callers driving the swarm themselves must pass every
+    /// event here.
+    pub fn handle_event(&mut self, event: &SwarmEvent<CustomEvent>) {
+        let now = Instant::now();
+        match event {
+            SwarmEvent::ConnectionEstablished { peer_id, connection_id, .. } => {
+                let is_bootstrap_peer = self.bootstrap.connected(*connection_id, *peer_id);
+                if is_bootstrap_peer {
+                    self.swarm.behaviour_mut().bootstrap_dht();
+                }
+            }
+            // Peers announce their listen addresses over identify; those
+            // speaking Kademlia go into the routing table.
+            SwarmEvent::Behaviour(CustomEvent::Identify(identify::Event::Received { peer_id, info, .. }))
+                if info.protocols.contains(&kad::PROTOCOL_NAME) =>
+            {
+                for addr in &info.listen_addrs {
+                    self.swarm.behaviour_mut().add_peer_address(peer_id, addr.clone());
+                }
+            }
+            SwarmEvent::Behaviour(CustomEvent::Kademlia(kad::Event::RoutingUpdated { peer, addresses, is_new_peer: true, .. })) => {
+                self.dial_discovered(*peer, addresses.iter().cloned().collect(), "the DHT");
+            }
+            // Peers found over mDNS are dialed like DHT discoveries, and so
+            // join the gossipsub mesh without any dial list.
+            SwarmEvent::Behaviour(CustomEvent::Mdns(mdns::Event::Discovered(peers))) => {
+                for (peer_id, addr) in peers {
+                    self.swarm.behaviour_mut().add_peer_address(peer_id, addr.clone());
+                    self.dial_discovered(*peer_id, vec![addr.clone()], "mDNS");
+                }
+            }
+            SwarmEvent::Behaviour(CustomEvent::Mdns(mdns::Event::Expired(peers))) => {
+                for (peer_id, addr) in peers {
+                    self.swarm.behaviour_mut().remove_peer_address(peer_id, addr);
+                }
+            }
+            SwarmEvent::OutgoingConnectionError { connection_id, error, .. } if self.bootstrap.is_dial(*connection_id) => {
+                println!("Failed to connect to bootstrap peer: {}", error);
+                self.bootstrap.dial_failed(*connection_id, now);
+            }
+            SwarmEvent::ConnectionClosed { peer_id, num_established: 0, .. } => {
+                self.bootstrap.disconnected(*peer_id, now);
+            }
+            _ => {}
+        }
+    }
+
+    /// Connects to a newly discovered peer unless already connected or
+    /// dialing it.
+    fn dial_discovered(&mut self, peer_id: PeerId, addresses: Vec<Multiaddr>, source: &str) {
+        if self.swarm.is_connected(&peer_id) {
+            return;
+        }
+        match self.swarm.dial(DialOpts::peer_id(peer_id).addresses(addresses).build()) {
+            Ok(()) => println!("Discovered peer {} through {}, dialing", peer_id, source),
+            Err(DialError::DialPeerConditionFalse(_)) => {}
+            Err(e) => println!("Failed to dial discovered peer {}: {}", peer_id, e),
+        }
+    }
+
+    /// Dials every bootstrap peer whose backoff has run out.
+    pub fn redial_due(&mut self) {
+        let now = Instant::now();
+        for index in self.bootstrap.due(now) {
+            let addr = self.bootstrap.peers()[index].addr.clone();
+            let opts = DialOpts::unknown_peer_id().address(addr.clone()).build();
+            let connection_id = opts.connection_id();
+            self.bootstrap.dialing(index, connection_id);
+            match self.swarm.dial(opts) {
+                Ok(()) => println!("Dialing bootstrap peer {}", addr),
+                Err(e) => {
+                    println!("Failed to dial bootstrap peer {}: {}", addr, e);
+                    self.bootstrap.dial_failed(connection_id, now);
+                }
+            }
+        }
+    }
+
+    /// Answers a sync request from `chain`, as every node does for its peers.
+    pub fn answer_sync_request(&mut self, peer_id: PeerId, request: SyncRequest, channel: ResponseChannel<SyncResponse>, chain: &Chain) {
+        if self.swarm.behaviour_mut().send_sync_response(channel, request.answer(chain)).is_err() {
+            println!("Could not answer sync request {:?} from {}", request, peer_id);
+        }
+    }
+
+    /// Sends `request` to `peer_id` and drives the swarm until it is
+    /// answered, answering peers' own sync requests from `chain` meanwhile.
+    async fn sync_request(&mut self, peer_id: PeerId, request: SyncRequest, chain: &Chain) -> Result<SyncResponse, String> {
+        let id = self.swarm.behaviour_mut().send_sync_request(&peer_id, request);
+        loop {
+            match self.next_event().await {
+                SwarmEvent::Behaviour(CustomEvent::Sync(request_response::Event::Message { message, peer, .. })) => match message {
+                    request_response::Message::Response { request_id, response } if request_id == id => return Ok(response),
+                    request_response::Message::Request { request, channel, .. } => self.answer_sync_request(peer, request, channel, chain),
+                    _ => {}
+                },
+                SwarmEvent::Behaviour(CustomEvent::Sync(request_response::Event::OutboundFailure { request_id, error, .. })) if request_id == id => {
+                    return Err(format!("Sync request to {} failed: {}", peer_id, error));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Brings `blockchain` up to the best chain among connected peers, then
+    /// joins live gossip. Waits up to `SYNC_PEER_TIMEOUT` for a first
+    /// bootstrap connection. Headers of the best peer's main chain are
+    /// fetched from our height on, stepping back until they connect to a
+    /// block we hold, and the missing blocks are then fetched in batches and
+    /// imported, so fork choice decides whether to reorganize onto them. A
+    /// peer whose chain forks off more than `MAX_REORG_DEPTH` blocks below
+    /// our head is refused. Returns the number of blocks imported.
+    pub async fn catch_up(&mut self, blockchain: &mut Blockchain) -> Result<u64, String> {
+        let imported = self.sync_from_best_peer(blockchain).await;
+        self.join_gossip();
+        imported
+    }
+
+    async fn sync_from_best_peer(&mut self, blockchain: &mut Blockchain) -> Result<u64, String> {
+        if !self.bootstrap.peers().is_empty() && self.swarm.connected_peers().next().is_none() {
+            let connected = async {
+                while self.swarm.connected_peers().next().is_none() {
+                    self.next_event().await;
+                }
+            };
+            if tokio::time::timeout(SYNC_PEER_TIMEOUT, connected).await.is_err() {
+                println!("No peers to sync from; starting from height {}", blockchain.chain().height());
+                return Ok(0);
+            }
+        }
+
+        let mut best: Option<(PeerId, ChainStatus)> = None;
+        for peer_id in self.connected_peers() {
+            match self.sync_request(peer_id, SyncRequest::GetStatus, blockchain.chain()).await {
+                Ok(SyncResponse::Status(status)) => {
+                    if status.work > best.as_ref().map_or(blockchain.chain().work(), |(_, best)| best.work) {
+                        best = Some((peer_id, status));
+                    }
+                }
+                Ok(other) => println!("Peer {} answered GetStatus with {:?}", peer_id, other),
+                Err(e) => println!("{}", e),
+            }
+        }
+        let Some((peer_id, status)) = best else {
+            return Ok(0);
+        };
+        println!("Syncing from {} at height {} ({})", peer_id, status.height, status.hash);
+
+        let mut imported = 0;
+        let mut from = blockchain.chain().height() + 1;
+        loop {
+            let request = SyncRequest::GetHeaders { from, count: MAX_HEADERS };
+            let headers = match self.sync_request(peer_id, request, blockchain.chain()).await? {
+                SyncResponse::Headers(headers) => headers,
+                other => return Err(format!("Peer {} answered GetHeaders with {:?}", peer_id, other)),
+            };
+            let Some(first) = headers.first() else {
+                break;
+            };
+            sync::verify_headers(&headers)?;
+            if first.index != from {
+                return Err(format!("Peer {} sent headers from {} instead of {}", peer_id, first.index, from));
+            }
+            if !blockchain.chain().contains(&first.previous_hash) {
+                // Import refuses forks deeper than MAX_REORG_DEPTH, so stop looking there.
+                let lowest = (blockchain.chain().height() + 1).saturating_sub(MAX_REORG_DEPTH).max(1);
+                if from <= 1 {
+                    return Err(format!("Peer {}'s chain does not share our genesis block", peer_id));
+                }
+                if from <= lowest {
+                    return Err(format!("Peer {}'s chain forks off more than {} blocks below our head", peer_id, MAX_REORG_DEPTH));
+                }
+                from = from.saturating_sub(MAX_HEADERS).max(lowest);
+                continue;
+            }
+
+            let missing: Vec<String> = headers.iter().filter(|header| !blockchain.chain().contains(&header.hash)).map(|header| header.hash.clone()).collect();
+            for hashes in missing.chunks(MAX_BLOCKS) {
+                let request = SyncRequest::GetBlocks { hashes: hashes.to_vec() };
+                let blocks = match self.sync_request(peer_id, request, blockchain.chain()).await? {
+                    SyncResponse::Blocks(blocks) => blocks,
+                    other => return Err(format!("Peer {} answered GetBlocks with {:?}", peer_id, other)),
+                };
+                if blocks.iter().map(|block| &block.hash).ne(hashes.iter()) {
+                    return Err(format!("Peer {} did not send the blocks requested", peer_id));
+                }
+                for block in blocks {
+                    let index = block.index;
+                    blockchain.import_block(block).map_err(|e| format!("Block {} from {} rejected: {}", index, peer_id, e))?;
+                    imported += 1;
+                }
+            }
+            if (headers.len() as u64) < MAX_HEADERS {
+                break;
+            }
+            from = headers[headers.len() - 1].index + 1;
+        }
+        println!("Synced {} blocks; head is now {} at height {}", imported, blockchain.chain().head().hash, blockchain.chain().height());
+        Ok(imported)
+    }
+
+    /// Subscribes to the gossip topics. `catch_up` does this once the node
+    /// has caught up, so it does not act on gossip far ahead of its chain.
+    pub fn join_gossip(&mut self) {
+        for topic in [blocks_topic(), actions_topic(), finality_topic()] {
+            self.swarm.behaviour_mut().subscribe(&topic);
+        }
+    }
+
     pub fn publish_message(&mut self, topic: IdentTopic, data: impl Into<Vec<u8>>) -> Result<(), Box<dyn std::error::Error>> {
         self.swarm.behaviour_mut().publish(topic, data)
     }
+
+    /// Announces a block this node produced.
+    pub fn publish_block(&mut self, block: &Block) -> Result<(), Box<dyn std::error::Error>> {
+        let data = serde_json::to_vec(block)?;
+        self.publish_message(blocks_topic(), data)
+    }
+
+    /// Relays a signed action so every producer can mine it.
+    pub fn publish_action(&mut self, signed: &SignedAction) -> Result<(), Box<dyn std::error::Error>> {
+        let data = serde_json::to_vec(signed)?;
+        self.publish_message(actions_topic(), data)
+    }
+
+    /// Gossips a finality vote to the other validators.
+    pub fn publish_vote(&mut self, vote: &Vote) -> Result<(), Box<dyn std::error::Error>> {
+        let data = serde_json::to_vec(vote)?;
+        self.publish_message(finality_topic(), data)
+    }
+
+    /// Acts on gossip from peers: blocks are imported and shown to
+    /// `gadget`, actions go to the mempool, and finality votes are counted
+    /// by `gadget`, with what they lead to applied by `apply_finality`.
+    /// Callers pass every swarm event here once the node has joined gossip.
+    pub fn handle_gossip(&mut self, event: &SwarmEvent<CustomEvent>, blockchain: &mut Blockchain, gadget: &mut FinalityGadget) {
+        let SwarmEvent::Behaviour(CustomEvent::Gossipsub(gossipsub::Event::Message { propagation_source, message, .. })) = event else {
+            return;
+        };
+        if message.topic == blocks_topic().hash() {
+            let Ok(block) = serde_json::from_slice::<Block>(&message.data) else {
+                println!("Ignoring malformed block from {}", propagation_source);
+                return;
+            };
+            let (index, hash) = (block.index, block.hash.clone());
+            match blockchain.import_block(block) {
+                Ok(()) => {
+                    let progress = gadget.on_block(index, &hash);
+                    self.apply_finality(progress, blockchain);
+                }
+                Err(e) => println!("Ignoring block {} from {}: {}", hash, propagation_source, e),
+            }
+        } else if message.topic == actions_topic().hash() {
+            let Ok(signed) = serde_json::from_slice::<SignedAction>(&message.data) else {
+                println!("Ignoring malformed action from {}", propagation_source);
+                return;
+            };
+            if let Err(e) = blockchain.submit_action(signed) {
+                println!("Ignoring action from {}: {}", propagation_source, e);
+            }
+        } else if let Some(vote) = decode_vote(message) {
+            match gadget.on_vote(vote) {
+                Ok(progress) => self.apply_finality(progress, blockchain),
+                Err(e) => println!("Ignoring finality vote from {}: {}", propagation_source, e),
+            }
+        }
+    }
+
+    /// Gossips the votes the finality gadget cast and finalizes the block it
+    /// reports final. Call with the `Progress` of every `on_block`, `on_vote`
+    /// and `tick`.
+    pub fn apply_finality(&mut self, progress: Progress, blockchain: &mut Blockchain) {
+        for vote in &progress.votes {
+            if let Err(e) = self.publish_vote(vote) {
+                println!("Failed to gossip {:?} for block {}: {}", vote.kind, vote.block_hash, e);
+            }
+        }
+        if let Some((height, hash)) = progress.finalized {
+            if let Err(e) = blockchain.finalize(&hash) {
+                println!("Cannot finalize block {} at height {}: {}", hash, height, e);
+            }
+        }
+    }
 }
 
 impl fmt::Debug for Network {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Network")
             .field("peer_id", &self.swarm.local_peer_id())
+            .field("bootstrap", &self.bootstrap)
             .finish()
     }
 }
@@ -85,10 +666,65 @@ impl fmt::Debug for Network {
 pub struct NetworkConfig {
     pub listen_addr: String,
     pub dial_addrs: Vec<String>,
+    /// Keyfile holding the node's identity, in the `node_keys.json` format
+    /// written by `generate-keys`. Created on first start if missing, so the
+    /// peer ID survives restarts. Without one a fresh identity is used.
+    #[serde(default)]
+    pub key_file: Option<PathBuf>,
+    /// Find peers on the local network over mDNS, for devnets started
+    /// without dial lists. mDNS does not run on loopback interfaces, so nodes
+    /// on one machine must listen on all interfaces, e.g.
+    /// `/ip4/0.0.0.0/tcp/4001`, to be reachable at the address it announces.
+    #[serde(default)]
+    pub mdns: bool,
 }
 
+/// `node_keys.json`: the hex protobuf encoding of the private key and the
+/// peer ID derived from it.
+#[derive(Serialize, Deserialize)]
+struct KeyFile {
+    private_key: String,
+    peer_id: String,
+}
+
+/// Loads the node identity from `path`, or generates one and saves it there
+/// if the file does not exist yet.
+pub fn load_or_create_keypair(path: &Path) -> io::Result<identity::Keypair> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), message));
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let keypair = identity::Keypair::generate_ed25519();
+            let key_file = KeyFile {
+                private_key: hex::encode(keypair.to_protobuf_encoding().map_err(|e| invalid(e.to_string()))?),
+                peer_id: keypair.public().to_peer_id().to_string(),
+            };
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(path, serde_json::to_vec_pretty(&key_file)?)?;
+            println!("Generated node identity {} in {}", key_file.peer_id, path.display());
+            return Ok(keypair);
+        }
+        Err(e) => return Err(e),
+    };
+    let key_file: KeyFile = serde_json::from_slice(&bytes)?;
+    let private_key = hex::decode(&key_file.private_key).map_err(|e| invalid(e.to_string()))?;
+    let keypair = identity::Keypair::from_protobuf_encoding(&private_key).map_err(|e| invalid(e.to_string()))?;
+    let peer_id = keypair.public().to_peer_id();
+    if peer_id.to_string() != key_file.peer_id {
+        return Err(invalid(format!("peer ID {} does not match the private key's {}", key_file.peer_id, peer_id)));
+    }
+    Ok(keypair)
+}
+
+/// Starts listening and dialing the bootstrap peers. The node joins gossip
+/// through `Network::catch_up`, or `Network::join_gossip` if it skips sync.
 pub async fn setup_network(config: NetworkConfig) -> Result<Network, Box<dyn std::error::Error>> {
-    let local_key = identity::Keypair::generate_ed25519();
+    let local_key = match &config.key_file {
+        Some(path) => load_or_create_keypair(path)?,
+        None => identity::Keypair::generate_ed25519(),
+    };
     let local_peer_id = PeerId::from(local_key.public());
     println!("Local peer ID: {}", local_peer_id);
 
@@ -111,6 +747,31 @@ pub async fn setup_network(config: NetworkConfig) -> Result<Network, Box<dyn std
 
     let ping = PingBehaviour::new(libp2p::ping::Config::new());
 
+    // Local nodes never get an external address confirmed, so Kademlia would
+    // otherwise stay in client mode and not answer other nodes' queries.
+    let mut kademlia = kad::Behaviour::new(local_peer_id, MemoryStore::new(local_peer_id));
+    kademlia.set_mode(Some(kad::Mode::Server));
+
+    let identify = identify::Behaviour::new(identify::Config::new(IDENTIFY_PROTOCOL.to_string(), local_key.public()));
+
+    let sync = request_response::json::Behaviour::new(
+        [(StreamProtocol::new(SYNC_PROTOCOL), ProtocolSupport::Full)],
+        request_response::Config::default(),
+    );
+
+    let mdns = if config.mdns {
+        let mdns = mdns::tokio::Behaviour::new(mdns::Config::default(), local_peer_id).map_err(|e| {
+            println!("mDNS init error: {}", e);
+            Box::new(std::io::Error::other(format!("mDNS init error: {}", e)))
+        })?;
+        if config.listen_addr.starts_with("/ip4/127.") {
+            println!("mDNS announces non-loopback addresses; listen on /ip4/0.0.0.0 instead of {} to be found", config.listen_addr);
+        }
+        Some(mdns)
+    } else {
+        None
+    };
+
     let transport = libp2p::tcp::tokio::Transport::default()
         .upgrade(libp2p::core::upgrade::Version::V1)
         .authenticate(
@@ -130,7 +791,7 @@ pub async fn setup_network(config: NetworkConfig) -> Result<Network, Box<dyn std
             println!("Transport setup error: {}", e);
             Box::new(std::io::Error::new(std::io::ErrorKind::Other, format!("Transport setup error: {}", e)))
         })?
-        .with_behaviour(|_| CombinedBehaviour { gossipsub, ping })
+        .with_behaviour(|_| CombinedBehaviour { gossipsub, ping, kademlia, identify, mdns: mdns.into(), sync })
         .map_err(|e| {
             println!("Behaviour setup error: {}", e);
             Box::new(std::io::Error::new(std::io::ErrorKind::Other, format!("Behaviour setup error: {}", e)))
@@ -149,5 +810,22 @@ pub async fn setup_network(config: NetworkConfig) -> Result<Network, Box<dyn std
             Box::new(std::io::Error::new(std::io::ErrorKind::Other, format!("Listen error: {}", e)))
         })?;
 
-    Ok(Network { swarm })
+    let dial_addrs = config.dial_addrs.iter()
+        .map(|addr| addr.parse::<Multiaddr>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| {
+            println!("Dial address parse error: {}", e);
+            Box::new(std::io::Error::other(format!("Dial address parse error: {}", e)))
+        })?;
+
+    let bootstrap = BootstrapPeers::new(dial_addrs, Instant::now());
+    for peer in bootstrap.peers() {
+        if let Some(peer_id) = peer.peer_id {
+            swarm.behaviour_mut().add_peer_address(&peer_id, peer.addr.clone());
+        }
+    }
+
+    let mut network = Network { swarm, bootstrap };
+    network.redial_due();
+    Ok(network)
 }
\ No newline at end of file
@@ -0,0 +1,24 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use sha2::{Digest, Sha256};
+
+/// RNG used by state transitions. ChaCha8 gives the same stream on every
+/// platform and `rand` version, which `thread_rng` and `StdRng` do not.
+pub type DeterministicRng = ChaCha8Rng;
+
+/// Seeds an RNG from the block being applied, the action within it and the
+/// action's position, so every node executing that block draws the same numbers.
+pub fn seeded_rng(block_hash: &str, action_hash: &str, counter: u64) -> DeterministicRng {
+    let mut hasher = Sha256::new();
+    hasher.update(block_hash.as_bytes());
+    hasher.update([0u8]);
+    hasher.update(action_hash.as_bytes());
+    hasher.update([0u8]);
+    hasher.update(counter.to_be_bytes());
+    DeterministicRng::from_seed(hasher.finalize().into())
+}
+
+/// Placeholder until the runtime is seeded for a specific action.
+pub fn unseeded() -> DeterministicRng {
+    seeded_rng("", "", 0)
+}
@@ -1,7 +1,25 @@
 use rand::Rng;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use serde::{Serialize, Deserialize};
 use std::time::{SystemTime, UNIX_EPOCH};
+use crate::amount::Amount;
+use crate::auth::SignedAction;
+use crate::error::GameError;
+use crate::ledger::{Ledger, MTOSHI, SOLUTIO};
+use crate::rng::{self, DeterministicRng};
+use crate::staking::StakingLedger;
+use crate::state::{self, StateLeaf};
+
+/// Expected number of hashes needed to find a block when nothing else is configured.
+pub const DEFAULT_DIFFICULTY: u64 = 1_000;
+/// Upper bound on nonces tried by a single `mine_block` call before giving up.
+pub const MAX_NONCE_ATTEMPTS: u64 = 10_000_000;
+/// Solutio paid out for every block.
+pub const BLOCK_REWARD: Amount = Amount::from_units(50);
+/// Share of the block reward a proof-of-work miner keeps once anyone has staked.
+pub const MINER_REWARD_PERCENT: u64 = 50;
+/// MTOSHI shared out by `distribute_mtoshi_rewards` in proportion to activity points.
+const ACTIVITY_REWARD: Amount = Amount::from_units(500);
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Item {
@@ -12,8 +30,6 @@ pub struct Item {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Player {
     pub id: String,
-    pub solutio_balance: u64,
-    pub mtoshi_balance: u64,
     pub activity_points: u64,
     pub level: u32,
     pub experience: u64,
@@ -25,8 +41,6 @@ impl Player {
     pub fn new(id: String) -> Self {
         Player {
             id,
-            solutio_balance: 0,
-            mtoshi_balance: 0,
             activity_points: 0,
             level: 1,
             experience: 0,
@@ -55,69 +69,172 @@ impl Player {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct GameFiRuntime {
     pub players: HashMap<String, Player>,
-    pool_balance: u64,
-    mining_pool: HashMap<String, u64>,
+    ledger: Ledger,
+    pool_balance: Amount,
+    staking: StakingLedger,
+    difficulty: u64,
+    #[serde(skip, default = "rng::unseeded")]
+    rng: DeterministicRng,
 }
 
 impl GameFiRuntime {
     pub fn new() -> Self {
         GameFiRuntime {
             players: HashMap::new(),
-            pool_balance: 1_000_000,
-            mining_pool: HashMap::new(),
+            ledger: Ledger::default(),
+            pool_balance: Amount::from_units(1_000_000),
+            staking: StakingLedger::default(),
+            difficulty: DEFAULT_DIFFICULTY,
+            rng: rng::unseeded(),
         }
     }
 
-    pub fn mine_block(&mut self, previous_hash: &str, miner: String, index: u64) -> Option<catena_node::Block> {
-        let nonce = rand::thread_rng().gen::<u64>();
-        let computed_hash: u64 = rand::thread_rng().gen();
-
-        if computed_hash % 1000 < 100 {
-            let base_reward = 50;
-            let mut transactions = vec![];
-
-            *self.mining_pool.entry(miner.clone()).or_insert(0) += base_reward;
-            let total_contribution: u64 = self.mining_pool.values().sum();
-
-            for (player_id, contribution) in self.mining_pool.iter() {
-                let player_reward = (contribution * base_reward) / total_contribution;
-                if let Some(player) = self.players.get_mut(player_id) {
-                    player.solutio_balance += player_reward;
-                    player.gain_experience(player_reward * 2);
-                    if rand::thread_rng().gen_bool(0.3) {
-                        player.add_item(Item {
-                            name: "Mining Gem".to_string(),
-                            value: 10,
-                        });
-                    }
-                    transactions.push(catena_node::Transaction {
-                        sender: "system".to_string(),
-                        receiver: player_id.clone(),
-                        amount: player_reward.into(),
-                        token: "Solutio".to_string(),
-                    });
-                }
+    pub fn difficulty(&self) -> u64 {
+        self.difficulty
+    }
+
+    pub fn set_difficulty(&mut self, difficulty: u64) {
+        self.difficulty = difficulty.max(1);
+    }
+
+    /// Reseeds the runtime's RNG before applying an action. All randomness in
+    /// state transitions comes from here, never from `thread_rng`.
+    pub fn seed_rng(&mut self, block_hash: &str, action_hash: &str, counter: u64) {
+        self.rng = rng::seeded_rng(block_hash, action_hash, counter);
+    }
+
+    pub fn ledger(&self) -> &Ledger {
+        &self.ledger
+    }
+
+    pub fn ledger_mut(&mut self) -> &mut Ledger {
+        &mut self.ledger
+    }
+
+    pub fn staking(&self) -> &StakingLedger {
+        &self.staking
+    }
+
+    pub fn staking_mut(&mut self) -> &mut StakingLedger {
+        &mut self.staking
+    }
+
+    /// Moves `amount` of the player's MTOSHI into their stake, returning the
+    /// new stake.
+    pub fn stake(&mut self, player_id: &str, amount: Amount) -> Result<Amount, GameError> {
+        if !self.players.contains_key(player_id) {
+            return Err(GameError::not_found("Player", player_id));
+        }
+        self.ledger.require(player_id, MTOSHI, amount)?;
+        let stake = self.staking.stake(player_id, amount)?;
+        self.ledger.debit(player_id, MTOSHI, amount)?;
+        println!("{} staked {} MTOSHI (total stake {})", player_id, amount, stake);
+        Ok(stake)
+    }
+
+    /// Starts unbonding `amount` of the player's stake at `height`, returning
+    /// the height at which it is released back to their balance.
+    pub fn unstake(&mut self, player_id: &str, amount: Amount, height: u64) -> Result<u64, GameError> {
+        let release_height = self.staking.unstake(player_id, amount, height)?;
+        println!("{} unstaked {} MTOSHI, released at height {}", player_id, amount, release_height);
+        Ok(release_height)
+    }
+
+    /// Credits the player's accrued staking rewards to their Solutio balance.
+    pub fn claim_rewards(&mut self, player_id: &str) -> Result<Amount, GameError> {
+        if !self.players.contains_key(player_id) {
+            return Ok(Amount::ZERO);
+        }
+        self.ledger.credit(player_id, SOLUTIO, self.staking.rewards_of(player_id))?;
+        Ok(self.staking.claim(player_id))
+    }
+
+    /// Returns unbonded stake that matures at `height` to its owners.
+    pub fn release_unbonded(&mut self, height: u64) -> Result<Vec<catena_node::Transaction>, GameError> {
+        let mut transactions = vec![];
+        for (staker, amount) in self.staking.release(height)? {
+            if self.players.contains_key(&staker) {
+                self.ledger.credit(&staker, MTOSHI, amount)?;
             }
+            transactions.push(catena_node::Transaction {
+                sender: "unbonding".to_string(),
+                receiver: staker,
+                amount: amount.units().into(),
+                token: MTOSHI.to_string(),
+            });
+        }
+        Ok(transactions)
+    }
 
-            let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
-            let hash = format!("{:x}", computed_hash);
+    /// Pays the block reward, returning the payouts for the block's
+    /// transaction list. Once anyone has staked, the reward accrues to
+    /// stakers pro rata and is collected with `ClaimRewards`, except for the
+    /// `MINER_REWARD_PERCENT` a proof-of-work miner keeps. Until then the
+    /// block producer is paid directly. A paid producer may find a gem,
+    /// seeded from the parent block so the producer and every importer agree
+    /// on it.
+    pub fn distribute_block_reward(&mut self, producer: &str, previous_hash: &str, proof_of_work: bool) -> Result<Vec<catena_node::Transaction>, GameError> {
+        let reward_transaction = |receiver: String, amount: Amount| catena_node::Transaction {
+            sender: "system".to_string(),
+            receiver,
+            amount: amount.units().into(),
+            token: SOLUTIO.to_string(),
+        };
+        let mut transactions = vec![];
+        let mut producer_reward = BLOCK_REWARD;
+        if self.staking.has_stakers() {
+            producer_reward = if proof_of_work { BLOCK_REWARD.percent(MINER_REWARD_PERCENT)? } else { Amount::ZERO };
+            let shares = self.staking.distribute(BLOCK_REWARD.checked_sub(producer_reward)?)?;
+            println!("Staking reward distribution: {:?}", shares);
+            transactions.extend(shares.into_iter().map(|(staker, share)| reward_transaction(staker, share)));
+        }
 
-            println!("Mining pool distribution: {:?}", transactions);
-            Some(catena_node::Block {
-                index,
-                timestamp,
-                transactions,
-                previous_hash: previous_hash.to_string(),
-                hash,
-                nonce,
-            })
-        } else {
-            None
+        let Some(player) = self.players.get_mut(producer).filter(|_| !producer_reward.is_zero()) else {
+            return Ok(transactions);
+        };
+        self.ledger.credit(producer, SOLUTIO, producer_reward)?;
+        player.gain_experience(producer_reward.units() * 2);
+        self.rng = rng::seeded_rng(previous_hash, "block-reward", 0);
+        if self.rng.gen_bool(0.3) {
+            player.add_item(Item {
+                name: "Mining Gem".to_string(),
+                value: 10,
+            });
+        }
+        transactions.push(reward_transaction(producer.to_string(), producer_reward));
+        Ok(transactions)
+    }
+
+    /// Seals an already-applied block body under a proof-of-work header.
+    pub fn mine_block(
+        &self,
+        previous_hash: &str,
+        index: u64,
+        transactions: Vec<catena_node::Transaction>,
+        actions: Vec<SignedAction>,
+        state_root: String,
+    ) -> Option<catena_node::Block> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let mut block = catena_node::Block::new(index, timestamp, previous_hash.to_string(), transactions, actions, state_root, self.difficulty);
+
+        if !block.mine(MAX_NONCE_ATTEMPTS) {
+            println!("No valid nonce found for block {} at difficulty {}", index, self.difficulty);
+            return None;
         }
+
+        println!("Mined block {} with nonce {} and {} actions: {}", block.index, block.nonce, block.actions.len(), block.hash);
+        Some(block)
+    }
+
+    /// Checks that a block received from another node carries a hash that
+    /// recomputes from its header and satisfies the difficulty it declares.
+    /// Whether that difficulty is the right one for its height is `Chain`'s call.
+    pub fn validate_block(&self, block: &catena_node::Block) -> Result<(), String> {
+        block.verify()
     }
 
     pub fn record_activity(&mut self, player_id: &str, activity_type: u32) {
@@ -131,7 +248,7 @@ impl GameFiRuntime {
             };
             player.activity_points += points;
             player.gain_experience(xp);
-            if rand::thread_rng().gen_bool(item_chance) {
+            if self.rng.gen_bool(item_chance) {
                 player.add_item(Item {
                     name: format!("Activity {} Reward", activity_type),
                     value: xp / 2,
@@ -141,7 +258,8 @@ impl GameFiRuntime {
         }
     }
 
-    pub fn battle(&mut self, player1_id: &str, player2_id: &str) {
+    /// Fights a battle and returns the winner's id.
+    pub fn battle(&mut self, player1_id: &str, player2_id: &str) -> String {
         let mut player1 = self.players.remove(player1_id).unwrap_or_else(|| {
             println!("Player {} not found!", player1_id);
             Player::new(player1_id.to_string())
@@ -152,33 +270,36 @@ impl GameFiRuntime {
             Player::new(player2_id.to_string())
         });
 
-        let player1_roll = player1.strength + rand::thread_rng().gen_range(0..10);
-        let player2_roll = player2.strength + rand::thread_rng().gen_range(0..10);
+        let player1_roll = player1.strength + self.rng.gen_range(0..10);
+        let player2_roll = player2.strength + self.rng.gen_range(0..10);
         println!("Battle: {} (strength: {}) vs {} (strength: {})", player1_id, player1_roll, player2_id, player2_roll);
-        if player1_roll >= player2_roll {
+        let winner = if player1_roll >= player2_roll {
             player1.gain_experience(30);
             player1.activity_points += 15;
             println!("{} wins the battle!", player1_id);
-            if rand::thread_rng().gen_bool(0.5) {
+            if self.rng.gen_bool(0.5) {
                 player1.add_item(Item {
                     name: "Battle Trophy".to_string(),
                     value: 20,
                 });
             }
+            player1_id
         } else {
             player2.gain_experience(30);
             player2.activity_points += 15;
             println!("{} wins the battle!", player2_id);
-            if rand::thread_rng().gen_bool(0.5) {
+            if self.rng.gen_bool(0.5) {
                 player2.add_item(Item {
                     name: "Battle Trophy".to_string(),
                     value: 20,
                 });
             }
-        }
+            player2_id
+        };
 
         self.players.insert(player1_id.to_string(), player1);
         self.players.insert(player2_id.to_string(), player2);
+        winner.to_string()
     }
 
     pub fn trade_items(&mut self, player1_id: &str, player1_item_index: usize, player2_id: &str, player2_item_index: usize) -> bool {
@@ -214,24 +335,28 @@ impl GameFiRuntime {
         true
     }
 
-    pub fn distribute_mtoshi_rewards(&mut self) {
-        let total_points: u64 = self.players.values().map(|p| p.activity_points).sum();
-        if total_points == 0 {
-            return;
+    /// Shares `ACTIVITY_REWARD` out of the pool by activity points and
+    /// returns the amount paid. Nothing changes if any share would overflow.
+    pub fn distribute_mtoshi_rewards(&mut self) -> Result<Amount, GameError> {
+        let total_points = self.players.values().try_fold(0u64, |total, p| total.checked_add(p.activity_points));
+        let total_points = total_points.ok_or_else(|| GameError::Overflow { operation: "sum of activity points".to_string() })?;
+        if total_points == 0 || ACTIVITY_REWARD > self.pool_balance {
+            return Ok(Amount::ZERO);
         }
 
-        let total_reward = 500;
-        if total_reward > self.pool_balance {
-            return;
+        let mut balances = vec![];
+        for (id, player) in &self.players {
+            let player_reward = ACTIVITY_REWARD.mul_div(player.activity_points, total_points)?;
+            self.ledger.balance(id, MTOSHI).checked_add(player_reward)?;
+            balances.push((id.clone(), player_reward));
         }
-
-        for player in self.players.values_mut() {
-            let player_reward = (player.activity_points * total_reward) / total_points;
-            player.mtoshi_balance += player_reward;
-            player.activity_points = 0;
-            println!("{} received {} MTOSHI", player.id, player_reward);
+        self.pool_balance = self.pool_balance.checked_sub(ACTIVITY_REWARD)?;
+        for (id, player_reward) in balances {
+            self.ledger.credit(&id, MTOSHI, player_reward)?;
+            self.players.get_mut(&id).expect("player exists").activity_points = 0;
+            println!("{} received {} MTOSHI", id, player_reward.units());
         }
-        self.pool_balance -= total_reward;
+        Ok(ACTIVITY_REWARD)
     }
 
     pub fn show_leaderboard(&self) {
@@ -240,18 +365,33 @@ impl GameFiRuntime {
         println!("\nLeaderboard:");
         for (rank, (id, player)) in leaderboard.iter().enumerate() {
             println!("{}. {} - Level: {}, XP: {}, Solutio: {}, MTOSHI: {}", 
-                rank + 1, id, player.level, player.experience, player.solutio_balance, player.mtoshi_balance);
+                rank + 1, id, player.level, player.experience, self.ledger.balance(id, SOLUTIO), self.ledger.balance(id, MTOSHI));
         }
     }
 
-    pub fn get_mtoshi_balance(&self, player_id: &str) -> Option<u64> {
-        self.players.get(player_id).map(|player| player.mtoshi_balance)
+    /// This runtime's part of the world state committed in block state roots.
+    pub fn state_leaves(&self) -> Vec<StateLeaf> {
+        let mut leaves = vec![state::leaf("runtime/pool_balance".to_string(), &self.pool_balance)];
+        leaves.extend(self.players.iter().map(|(id, player)| state::leaf(format!("player/{}", id), player)));
+        leaves.extend(self.ledger.accounts().map(|(id, balances)| state::leaf(format!("balance/{}", id), balances)));
+        // Players that never held a token still get an empty balances leaf,
+        // so account proofs can always prove what they hold.
+        let no_balances = BTreeMap::<String, Amount>::new();
+        leaves.extend(
+            self.players
+                .keys()
+                .filter(|id| self.ledger.balances(id).is_none())
+                .map(|id| state::leaf(format!("balance/{}", id), &no_balances)),
+        );
+        leaves.push(state::leaf("staking".to_string(), &self.staking));
+        leaves
     }
 
     pub fn simulate_player_activity(&mut self) {
-        let player_ids: Vec<String> = self.players.keys().cloned().collect();
+        let mut player_ids: Vec<String> = self.players.keys().cloned().collect();
+        player_ids.sort();
         for player_id in player_ids {
-            let activity = rand::thread_rng().gen_range(0..4);
+            let activity = self.rng.gen_range(0..4);
             match activity {
                 0 => self.record_activity(&player_id, 1),
                 1 => self.record_activity(&player_id, 2),
@@ -264,7 +404,25 @@ impl GameFiRuntime {
 }
 
 pub mod catena_node {
+    use libp2p::identity::{Keypair, PublicKey};
     use serde::{Serialize, Deserialize};
+    use sha2::{Digest, Sha256};
+    use crate::auth::{self, SignedAction};
+    use crate::merkle::{self, MerkleProof};
+
+    pub fn sha256_hex(data: &[u8]) -> String {
+        hex::encode(Sha256::digest(data))
+    }
+
+    /// A hash meets `difficulty` when its leading 64 bits are no greater than
+    /// `u64::MAX / difficulty`, so on average `difficulty` hashes are needed.
+    pub fn meets_difficulty(hash: &str, difficulty: u64) -> bool {
+        let prefix = match hash.get(..16).and_then(|p| u64::from_str_radix(p, 16).ok()) {
+            Some(prefix) => prefix,
+            None => return false,
+        };
+        prefix <= u64::MAX / difficulty.max(1)
+    }
 
     #[derive(Debug, Clone, Serialize, Deserialize)]
     pub struct Transaction {
@@ -274,13 +432,183 @@ pub mod catena_node {
         pub token: String,
     }
 
+    impl Transaction {
+        /// Bytes committed to as this transaction's leaf in the block's `tx_root`.
+        pub fn leaf_bytes(&self) -> Vec<u8> {
+            serde_json::to_vec(self).expect("transaction serializes")
+        }
+    }
+
     #[derive(Debug, Clone, Serialize, Deserialize)]
     pub struct Block {
         pub index: u64,
         pub timestamp: u64,
         pub transactions: Vec<Transaction>,
+        pub tx_root: String,
+        /// Signed actions taken from the mempool, applied in this order.
+        pub actions: Vec<SignedAction>,
+        pub actions_root: String,
+        /// Root of the world state after this block's actions and rewards.
+        pub state_root: String,
         pub previous_hash: String,
         pub hash: String,
         pub nonce: u64,
+        pub difficulty: u64,
+        /// Proof-of-authority slot the block was produced in; 0 under proof of work.
+        pub slot: u64,
+        /// Hex of the producing validator's protobuf-encoded public key, empty under proof of work.
+        pub validator: String,
+        /// Validator's hex signature over `hash`.
+        pub signature: String,
+    }
+
+    #[derive(Serialize)]
+    struct BlockHeader<'a> {
+        index: u64,
+        timestamp: u64,
+        previous_hash: &'a str,
+        tx_root: &'a str,
+        actions_root: &'a str,
+        state_root: &'a str,
+        difficulty: u64,
+        nonce: u64,
+        slot: u64,
+        validator: &'a str,
     }
-}
+
+    impl Block {
+        /// An unsealed block: `hash` is empty until it is mined or signed.
+        pub fn new(
+            index: u64,
+            timestamp: u64,
+            previous_hash: String,
+            transactions: Vec<Transaction>,
+            actions: Vec<SignedAction>,
+            state_root: String,
+            difficulty: u64,
+        ) -> Self {
+            Block {
+                index,
+                timestamp,
+                tx_root: Self::transactions_root(&transactions),
+                transactions,
+                actions_root: Self::actions_root(&actions),
+                actions,
+                state_root,
+                previous_hash,
+                hash: String::new(),
+                nonce: 0,
+                difficulty,
+                slot: 0,
+                validator: String::new(),
+                signature: String::new(),
+            }
+        }
+
+        /// Merkle root over the serialized transactions, in block order.
+        pub fn transactions_root(transactions: &[Transaction]) -> String {
+            let leaves: Vec<Vec<u8>> = transactions.iter().map(Transaction::leaf_bytes).collect();
+            merkle::merkle_root(&leaves)
+        }
+
+        pub fn actions_root(actions: &[SignedAction]) -> String {
+            let leaves: Vec<Vec<u8>> = actions.iter().map(SignedAction::leaf_bytes).collect();
+            merkle::merkle_root(&leaves)
+        }
+
+        /// Proof that the transaction at `index` is committed to by `tx_root`.
+        pub fn transaction_proof(&self, index: usize) -> Option<MerkleProof> {
+            let leaves: Vec<Vec<u8>> = self.transactions.iter().map(Transaction::leaf_bytes).collect();
+            merkle::build_proof(&leaves, index)
+        }
+
+        /// Checks a transaction against a block's `tx_root` without needing the
+        /// rest of the block, e.g. from a light wallet holding only headers.
+        pub fn verify_transaction(tx_root: &str, transaction: &Transaction, proof: &MerkleProof) -> bool {
+            merkle::verify_proof(tx_root, &transaction.leaf_bytes(), proof)
+        }
+
+        fn hash_header(&self, nonce: u64) -> String {
+            let header = BlockHeader {
+                index: self.index,
+                timestamp: self.timestamp,
+                previous_hash: &self.previous_hash,
+                tx_root: &self.tx_root,
+                actions_root: &self.actions_root,
+                state_root: &self.state_root,
+                difficulty: self.difficulty,
+                nonce,
+                slot: self.slot,
+                validator: &self.validator,
+            };
+            sha256_hex(&serde_json::to_vec(&header).expect("block header serializes"))
+        }
+
+        pub fn compute_hash(&self) -> String {
+            self.hash_header(self.nonce)
+        }
+
+        /// Searches nonces from zero until the header hash meets the block's
+        /// difficulty. On success `nonce` and `hash` are filled in and `true` is returned.
+        pub fn mine(&mut self, max_attempts: u64) -> bool {
+            for nonce in 0..max_attempts {
+                let hash = self.hash_header(nonce);
+                if meets_difficulty(&hash, self.difficulty) {
+                    self.nonce = nonce;
+                    self.hash = hash;
+                    return true;
+                }
+            }
+            false
+        }
+
+        /// Copy of the block without transactions and actions. The roots stay,
+        /// so the hash and seal can still be checked, e.g. in evidence.
+        pub fn without_body(&self) -> Block {
+            Block { transactions: vec![], actions: vec![], ..self.clone() }
+        }
+
+        /// Signs the block as the validator of `slot`. The hash covers the slot
+        /// and the validator's key, and the signature covers the hash.
+        pub fn seal(&mut self, keypair: &Keypair, slot: u64) {
+            self.slot = slot;
+            self.validator = hex::encode(keypair.public().encode_protobuf());
+            self.hash = self.compute_hash();
+            let signature = keypair.sign(self.hash.as_bytes()).expect("ed25519 signing does not fail");
+            self.signature = hex::encode(signature);
+        }
+
+        /// Checks the validator's signature over the hash, returning the
+        /// validator's account id.
+        pub fn verify_seal(&self) -> Result<String, String> {
+            let public_key = hex::decode(&self.validator)
+                .ok()
+                .and_then(|bytes| PublicKey::try_decode_protobuf(&bytes).ok())
+                .ok_or_else(|| format!("Block {} has an invalid validator key", self.index))?;
+            let signature = hex::decode(&self.signature).map_err(|_| format!("Block {} has a malformed signature", self.index))?;
+            if !public_key.verify(self.hash.as_bytes(), &signature) {
+                return Err(format!("Block {} has an invalid validator signature", self.index));
+            }
+            Ok(auth::account_id(&public_key))
+        }
+
+        pub fn verify(&self) -> Result<(), String> {
+            let tx_root = Self::transactions_root(&self.transactions);
+            if tx_root != self.tx_root {
+                return Err(format!("Block {} tx_root mismatch: expected {}, got {}", self.index, tx_root, self.tx_root));
+            }
+            let actions_root = Self::actions_root(&self.actions);
+            if actions_root != self.actions_root {
+                return Err(format!("Block {} actions_root mismatch: expected {}, got {}", self.index, actions_root, self.actions_root));
+            }
+            let computed = self.compute_hash();
+            if computed != self.hash {
+                return Err(format!("Block {} hash mismatch: expected {}, got {}", self.index, computed, self.hash));
+            }
+            if !meets_difficulty(&self.hash, self.difficulty) {
+                return Err(format!("Block {} hash {} does not meet difficulty {}", self.index, self.hash, self.difficulty));
+            }
+            Ok(())
+        }
+    }
+}
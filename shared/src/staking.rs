@@ -0,0 +1,214 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use crate::amount::Amount;
+use crate::error::GameError;
+
+/// Blocks an unstaked amount stays locked before it returns to the staker.
+pub const UNBONDING_BLOCKS: u64 = 100;
+/// Consecutive slots a validator may miss before it is jailed.
+pub const JAIL_MISSED_SLOTS: u64 = 20;
+/// Blocks a jailed validator has to sit out before it may unjail.
+pub const JAIL_PERIOD_BLOCKS: u64 = 50;
+/// Percentage of a validator's stake slashed for signing two blocks at one height.
+pub const DOUBLE_SIGN_SLASH_PERCENT: u64 = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StakingError {
+    ZeroAmount,
+    InsufficientStake { staked: Amount, requested: Amount },
+}
+
+impl fmt::Display for StakingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StakingError::ZeroAmount => write!(f, "Amount must be greater than zero"),
+            StakingError::InsufficientStake { staked, requested } => write!(f, "Cannot unstake {} with only {} staked", requested, staked),
+        }
+    }
+}
+
+impl std::error::Error for StakingError {}
+
+/// MTOSHI locked by stakers, amounts waiting out the unbonding period and
+/// block rewards accrued but not yet claimed. Maps are ordered so reward
+/// splits and the state root do not depend on insertion order.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StakingLedger {
+    stakes: BTreeMap<String, Amount>,
+    /// Per staker, amounts keyed by the height at which they are released.
+    unbonding: BTreeMap<String, BTreeMap<u64, Amount>>,
+    rewards: BTreeMap<String, Amount>,
+    /// Rounding remainder of earlier splits, added to the next reward.
+    carry: Amount,
+    /// Slots each validator has missed in a row.
+    missed: BTreeMap<String, u64>,
+    /// Validators left out of the schedule until they unjail, with the
+    /// height at which they were jailed.
+    jailed: BTreeMap<String, u64>,
+    /// Heights each validator has already been slashed for.
+    slashed: BTreeSet<(String, u64)>,
+}
+
+impl StakingLedger {
+    pub fn stake_of(&self, staker: &str) -> Amount {
+        self.stakes.get(staker).copied().unwrap_or_default()
+    }
+
+    pub fn total_stake(&self) -> Result<Amount, GameError> {
+        Amount::checked_sum(self.stakes.values().copied())
+    }
+
+    pub fn has_stakers(&self) -> bool {
+        !self.stakes.is_empty()
+    }
+
+    pub fn unbonding_of(&self, staker: &str) -> Vec<(u64, Amount)> {
+        self.unbonding.get(staker).map(|entries| entries.iter().map(|(height, amount)| (*height, *amount)).collect()).unwrap_or_default()
+    }
+
+    pub fn rewards_of(&self, staker: &str) -> Amount {
+        self.rewards.get(staker).copied().unwrap_or_default()
+    }
+
+    /// Locks `amount`, already taken from the staker's balance, and returns
+    /// their new stake.
+    pub fn stake(&mut self, staker: &str, amount: Amount) -> Result<Amount, GameError> {
+        if amount.is_zero() {
+            return Err(StakingError::ZeroAmount.into());
+        }
+        let stake = self.stake_of(staker).checked_add(amount)?;
+        self.stakes.insert(staker.to_string(), stake);
+        Ok(stake)
+    }
+
+    /// Starts unbonding `amount` at `height`, returning the height at which
+    /// it is released.
+    pub fn unstake(&mut self, staker: &str, amount: Amount, height: u64) -> Result<u64, GameError> {
+        let staked = self.stake_of(staker);
+        if amount.is_zero() {
+            return Err(StakingError::ZeroAmount.into());
+        }
+        if amount > staked {
+            return Err(StakingError::InsufficientStake { staked, requested: amount }.into());
+        }
+        let release_height = height + UNBONDING_BLOCKS;
+        let entries = self.unbonding.entry(staker.to_string()).or_default();
+        let unbonding = entries.get(&release_height).copied().unwrap_or_default().checked_add(amount)?;
+        entries.insert(release_height, unbonding);
+        if amount == staked {
+            self.stakes.remove(staker);
+        } else {
+            self.stakes.insert(staker.to_string(), staked.checked_sub(amount)?);
+        }
+        Ok(release_height)
+    }
+
+    /// Removes and returns every unbonding amount released at or before `height`.
+    pub fn release(&mut self, height: u64) -> Result<Vec<(String, Amount)>, GameError> {
+        let mut released = vec![];
+        for (staker, entries) in self.unbonding.iter_mut() {
+            let pending = entries.split_off(&(height + 1));
+            let matured = Amount::checked_sum(std::mem::replace(entries, pending).into_values())?;
+            if !matured.is_zero() {
+                released.push((staker.clone(), matured));
+            }
+        }
+        self.unbonding.retain(|_, entries| !entries.is_empty());
+        Ok(released)
+    }
+
+    /// Splits `reward` among stakers in proportion to their stake and
+    /// returns each staker's share. Nothing is split if no one has staked.
+    pub fn distribute(&mut self, reward: Amount) -> Result<Vec<(String, Amount)>, GameError> {
+        let total = self.total_stake()?;
+        if total.is_zero() {
+            return Ok(vec![]);
+        }
+        let pot = reward.checked_add(self.carry)?;
+        let mut paid = Amount::ZERO;
+        let mut shares = vec![];
+        for (staker, stake) in &self.stakes {
+            let share = pot.mul_div(stake.units(), total.units())?;
+            if !share.is_zero() {
+                let rewards = self.rewards.get(staker).copied().unwrap_or_default().checked_add(share)?;
+                self.rewards.insert(staker.clone(), rewards);
+                shares.push((staker.clone(), share));
+                paid = paid.checked_add(share)?;
+            }
+        }
+        self.carry = pot.checked_sub(paid)?;
+        Ok(shares)
+    }
+
+    /// Weight a validator is scheduled with: its stake in base units, or
+    /// `None` while jailed.
+    pub fn validator_weight(&self, validator: &str) -> Option<u64> {
+        if self.jailed.contains_key(validator) {
+            return None;
+        }
+        Some(self.stake_of(validator).units())
+    }
+
+    pub fn is_jailed(&self, validator: &str) -> bool {
+        self.jailed.contains_key(validator)
+    }
+
+    /// First height at which a jailed validator may unjail, `None` if it is
+    /// not jailed.
+    pub fn jail_release(&self, validator: &str) -> Option<u64> {
+        self.jailed.get(validator).map(|height| height + JAIL_PERIOD_BLOCKS)
+    }
+
+    /// Counts a missed slot and returns how many the validator has missed in a row.
+    pub fn record_missed(&mut self, validator: &str) -> u64 {
+        let missed = self.missed.entry(validator.to_string()).or_insert(0);
+        *missed += 1;
+        *missed
+    }
+
+    pub fn record_produced(&mut self, validator: &str) {
+        self.missed.remove(validator);
+    }
+
+    pub fn jail(&mut self, validator: &str, height: u64) {
+        println!("Jailed validator {} at block {}", validator, height);
+        self.jailed.insert(validator.to_string(), height);
+    }
+
+    /// Puts a jailed validator back in the schedule with a clean record.
+    pub fn unjail(&mut self, validator: &str) -> bool {
+        self.missed.remove(validator);
+        self.jailed.remove(validator).is_some()
+    }
+
+    /// Takes `percent` of the validator's stake, bonded and unbonding, for
+    /// misbehaving at `height` and returns the amount taken. `None` if it was
+    /// already slashed for that height.
+    pub fn slash(&mut self, validator: &str, height: u64, percent: u64) -> Result<Option<Amount>, GameError> {
+        if self.slashed.contains(&(validator.to_string(), height)) {
+            return Ok(None);
+        }
+        let cut = |amount: &mut Amount| -> Result<Amount, GameError> {
+            let slashed = amount.percent(percent)?;
+            *amount = amount.checked_sub(slashed)?;
+            Ok(slashed)
+        };
+        let mut total = Amount::ZERO;
+        if let Some(stake) = self.stakes.get_mut(validator) {
+            total = total.checked_add(cut(stake)?)?;
+        }
+        if let Some(entries) = self.unbonding.get_mut(validator) {
+            for amount in entries.values_mut() {
+                total = total.checked_add(cut(amount)?)?;
+            }
+        }
+        self.slashed.insert((validator.to_string(), height));
+        Ok(Some(total))
+    }
+
+    /// Takes all of a staker's accrued rewards.
+    pub fn claim(&mut self, staker: &str) -> Amount {
+        self.rewards.remove(staker).unwrap_or_default()
+    }
+}
@@ -0,0 +1,104 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use crate::merkle::{self, MerkleProof};
+use crate::runtime::Player;
+
+/// One entry of the world state: a key such as `player/<id>` and the JSON
+/// encoding of the key together with its value.
+pub type StateLeaf = (String, Vec<u8>);
+
+pub fn leaf<T: Serialize>(key: String, value: &T) -> StateLeaf {
+    let bytes = serde_json::to_vec(&(&key, value)).expect("state serializes");
+    (key, bytes)
+}
+
+fn sorted(mut leaves: Vec<StateLeaf>) -> Vec<StateLeaf> {
+    leaves.sort_by(|a, b| a.0.cmp(&b.0));
+    leaves
+}
+
+fn root_of_sorted(leaves: &[StateLeaf]) -> String {
+    let leaves: Vec<Vec<u8>> = leaves.iter().map(|(_, bytes)| bytes.clone()).collect();
+    merkle::merkle_root(&leaves)
+}
+
+/// Merkle root over the world state. Leaves are ordered by key, so the root
+/// does not depend on `HashMap` iteration order. Values must not contain
+/// `HashMap`s themselves; callers convert those to `BTreeMap`s first.
+pub fn state_root(leaves: Vec<StateLeaf>) -> String {
+    root_of_sorted(&sorted(leaves))
+}
+
+/// The world state as committed by the block at `height`, kept so proofs can
+/// be served against that block's state root while newer actions are applied.
+#[derive(Debug, Clone, Default)]
+pub struct CommittedState {
+    height: u64,
+    root: String,
+    leaves: Vec<StateLeaf>,
+}
+
+impl CommittedState {
+    pub fn new(height: u64, leaves: Vec<StateLeaf>) -> Self {
+        let leaves = sorted(leaves);
+        let root = root_of_sorted(&leaves);
+        CommittedState { height, root, leaves }
+    }
+
+    pub fn height(&self) -> u64 {
+        self.height
+    }
+
+    pub fn root(&self) -> &str {
+        &self.root
+    }
+
+    /// Inclusion proof for the value committed under `key`, or `None` if the
+    /// key is not part of the state.
+    pub fn prove<T: DeserializeOwned>(&self, key: &str) -> Option<StateProof<T>> {
+        let index = self.leaves.binary_search_by(|(k, _)| k.as_str().cmp(key)).ok()?;
+        let (_, value): (String, T) = serde_json::from_slice(&self.leaves[index].1).ok()?;
+        let leaves: Vec<Vec<u8>> = self.leaves.iter().map(|(_, bytes)| bytes.clone()).collect();
+        let proof = merkle::build_proof(&leaves, index)?;
+        Some(StateProof { key: key.to_string(), value, proof })
+    }
+}
+
+/// A value in the world state and the path from its leaf to a state root.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateProof<T> {
+    pub key: String,
+    pub value: T,
+    pub proof: MerkleProof,
+}
+
+/// A player's game state and token balances, proven against the state root
+/// of the block at `height`. Every player has a balances leaf, empty for
+/// players who never held any token, so both parts are always proven.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountProof {
+    pub height: u64,
+    pub state_root: String,
+    pub player: StateProof<Player>,
+    pub balances: StateProof<BTreeMap<String, u64>>,
+}
+
+pub fn verify_state_proof<T: Serialize>(state_root: &str, proof: &StateProof<T>) -> bool {
+    let (_, bytes) = leaf(proof.key.clone(), &proof.value);
+    merkle::verify_proof(state_root, &bytes, &proof.proof)
+}
+
+/// Light-client check of an `AccountProof` for `player` against the state
+/// root of a block header the client already trusts. Needs no other state.
+pub fn verify_account_proof(state_root: &str, player: &str, proof: &AccountProof) -> bool {
+    if proof.state_root != state_root {
+        return false;
+    }
+    if proof.player.key != format!("player/{}", player) || proof.player.value.id != player {
+        return false;
+    }
+    proof.balances.key == format!("balance/{}", player)
+        && verify_state_proof(state_root, &proof.player)
+        && verify_state_proof(state_root, &proof.balances)
+}
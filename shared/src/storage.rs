@@ -0,0 +1,109 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use crate::runtime::catena_node::Block;
+
+const BLOCKS_FILE: &str = "blocks.log";
+const STATE_FILE: &str = "state.json";
+
+/// World state after the block at `height`, and the height of the latest
+/// final block at that point.
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot<T> {
+    pub height: u64,
+    #[serde(default)]
+    pub finalized: u64,
+    pub state: T,
+}
+
+/// On-disk home of a node: an append-only log of blocks, one JSON object per
+/// line, and a snapshot of the world state taken after the latest block.
+#[derive(Debug, Clone)]
+pub struct Storage {
+    dir: PathBuf,
+}
+
+impl Storage {
+    pub fn open(dir: impl AsRef<Path>) -> io::Result<Self> {
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(Storage { dir: dir.as_ref().to_path_buf() })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn append_block(&self, block: &Block) -> io::Result<()> {
+        let mut line = serde_json::to_vec(block)?;
+        line.push(b'\n');
+        let mut file = OpenOptions::new().create(true).append(true).open(self.dir.join(BLOCKS_FILE))?;
+        file.write_all(&line)?;
+        file.sync_all()
+    }
+
+    /// Reads the block log. A partially written last line, left by a crash
+    /// mid-append, is cut off so later appends start on a clean line.
+    pub fn load_blocks(&self) -> io::Result<Vec<Block>> {
+        let contents = match fs::read_to_string(self.dir.join(BLOCKS_FILE)) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e),
+        };
+        let mut blocks = vec![];
+        let mut damaged = false;
+        for line in contents.split_terminator('\n') {
+            match serde_json::from_str(line) {
+                Ok(block) => blocks.push(block),
+                Err(e) => {
+                    println!("Dropping unreadable block log entry after block {}: {}", blocks.len(), e);
+                    damaged = true;
+                    break;
+                }
+            }
+        }
+        if damaged {
+            self.rewrite_blocks(&blocks)?;
+        }
+        Ok(blocks)
+    }
+
+    /// Replaces the block log with exactly `blocks`.
+    pub fn rewrite_blocks(&self, blocks: &[Block]) -> io::Result<()> {
+        let mut contents = vec![];
+        for block in blocks {
+            contents.extend(serde_json::to_vec(block)?);
+            contents.push(b'\n');
+        }
+        self.write_atomically(BLOCKS_FILE, &contents)
+    }
+
+    /// Writes the snapshot to a temporary file, syncs it and renames it over
+    /// the previous one, so a crash leaves either the old or the new snapshot.
+    pub fn save_snapshot<T: Serialize>(&self, height: u64, finalized: u64, state: &T) -> io::Result<()> {
+        let contents = serde_json::to_vec(&Snapshot { height, finalized, state })?;
+        self.write_atomically(STATE_FILE, &contents)
+    }
+
+    pub fn load_snapshot<T: DeserializeOwned>(&self) -> io::Result<Option<Snapshot<T>>> {
+        let contents = match fs::read(self.dir.join(STATE_FILE)) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        Ok(Some(serde_json::from_slice(&contents)?))
+    }
+
+    fn write_atomically(&self, name: &str, contents: &[u8]) -> io::Result<()> {
+        let tmp_path = self.dir.join(format!("{}.tmp", name));
+        let mut tmp = File::create(&tmp_path)?;
+        tmp.write_all(contents)?;
+        tmp.sync_all()?;
+        fs::rename(&tmp_path, self.dir.join(name))?;
+        // Persist the rename itself. Directories cannot be opened on Windows,
+        // where the rename is already durable, so failures are ignored.
+        let _ = File::open(&self.dir).and_then(|dir| dir.sync_all());
+        Ok(())
+    }
+}
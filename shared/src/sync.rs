@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+use crate::chain::Chain;
+use crate::runtime::catena_node::Block;
+
+/// Request-response protocol nodes use to fetch blocks they missed.
+pub const SYNC_PROTOCOL: &str = "/catena/sync/1.0.0";
+
+/// Most headers answered to one `GetHeaders`.
+pub const MAX_HEADERS: u64 = 512;
+/// Most blocks answered to one `GetBlocks`, keeping responses well under the
+/// codec's size limit.
+pub const MAX_BLOCKS: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SyncRequest {
+    /// Main-chain headers from height `from` on, at most `count` of them.
+    GetHeaders { from: u64, count: u64 },
+    /// Full blocks with these hashes, from the main chain or a fork.
+    GetBlocks { hashes: Vec<String> },
+    GetStatus,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SyncResponse {
+    /// Blocks without their bodies, oldest first.
+    Headers(Vec<Block>),
+    /// The requested blocks that were found, in request order.
+    Blocks(Vec<Block>),
+    Status(ChainStatus),
+}
+
+/// A node's main-chain head.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainStatus {
+    pub height: u64,
+    pub hash: String,
+    /// Cumulative work, which decides the best chain.
+    pub work: u128,
+}
+
+impl ChainStatus {
+    pub fn of(chain: &Chain) -> Self {
+        ChainStatus { height: chain.height(), hash: chain.head().hash.clone(), work: chain.work() }
+    }
+}
+
+impl SyncRequest {
+    /// Answers the request from `chain`, capping its size.
+    pub fn answer(&self, chain: &Chain) -> SyncResponse {
+        match self {
+            SyncRequest::GetHeaders { from, count } => {
+                let end = from.saturating_add((*count).min(MAX_HEADERS));
+                let headers = (*from..end).map_while(|height| chain.get_by_height(height)).map(Block::without_body).collect();
+                SyncResponse::Headers(headers)
+            }
+            SyncRequest::GetBlocks { hashes } => {
+                SyncResponse::Blocks(hashes.iter().take(MAX_BLOCKS).filter_map(|hash| chain.get_block(hash)).cloned().collect())
+            }
+            SyncRequest::GetStatus => SyncResponse::Status(ChainStatus::of(chain)),
+        }
+    }
+}
+
+/// Checks that `headers` hash correctly and each one builds on the one
+/// before it. Bodies and seals are checked when the blocks are imported.
+pub fn verify_headers(headers: &[Block]) -> Result<(), String> {
+    for header in headers {
+        let computed = header.compute_hash();
+        if computed != header.hash {
+            return Err(format!("Header {} hash mismatch: expected {}, got {}", header.index, computed, header.hash));
+        }
+    }
+    for pair in headers.windows(2) {
+        if pair[1].previous_hash != pair[0].hash || pair[1].index != pair[0].index + 1 {
+            return Err(format!("Header {} does not build on header {}", pair[1].index, pair[0].index));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,184 @@
+mod common;
+
+use common::{mine, Account};
+use proptest::prelude::*;
+use shared::amount::{Amount, UNITS_PER_TOKEN};
+use shared::blockchain::{Action, ActionResult, Blockchain};
+use shared::error::GameError;
+
+const DTOSHI: u64 = UNITS_PER_TOKEN;
+
+proptest! {
+    #[test]
+    fn checked_math_matches_wide_integers(a in any::<u64>(), b in any::<u64>(), numerator in any::<u64>(), denominator in 1..u64::MAX) {
+        let (x, y) = (Amount::from_units(a), Amount::from_units(b));
+        let sum = a as u128 + b as u128;
+        prop_assert_eq!(x.checked_add(y).ok(), u64::try_from(sum).ok().map(Amount::from_units));
+        prop_assert_eq!(x.checked_sub(y).ok(), a.checked_sub(b).map(Amount::from_units));
+        let scaled = a as u128 * numerator as u128 / denominator as u128;
+        prop_assert_eq!(x.mul_div(numerator, denominator).ok(), u64::try_from(scaled).ok().map(Amount::from_units));
+        prop_assert!(x.percent(b % 101).unwrap() <= x);
+        prop_assert_eq!(Amount::from_tokens(a).ok(), a.checked_mul(DTOSHI).map(Amount::from_units));
+    }
+}
+
+#[test]
+fn overflow_is_an_error() {
+    let max = Amount::from_units(u64::MAX);
+    assert!(matches!(max.checked_add(Amount::from_units(1)), Err(GameError::Overflow { .. })));
+    assert!(matches!(Amount::ZERO.checked_sub(Amount::from_units(1)), Err(GameError::Overflow { .. })));
+    assert!(matches!(Amount::from_tokens(u64::MAX), Err(GameError::Overflow { .. })));
+    assert_eq!(Amount::from_units(1_050_000_000).to_string(), "1.050000000");
+}
+
+#[derive(Debug, Clone)]
+enum Op {
+    Post { by: usize, post: u8 },
+    Like { by: usize, post: u8, paid: bool },
+    Comment { by: usize, post: u8 },
+    Boost { by: usize, post: u8, tokens: u64 },
+    Mint { by: usize },
+    Sell { by: usize, index: u32, price: u64 },
+    Buy { by: usize, seller: usize, index: u32 },
+    Stake { by: usize, amount: u64 },
+    Unstake { by: usize, amount: u64 },
+}
+
+fn amount() -> impl Strategy<Value = u64> {
+    prop_oneof![0..50 * DTOSHI, any::<u64>(), Just(u64::MAX)]
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    let by = 0..3usize;
+    let post = 0..3u8;
+    prop_oneof![
+        (by.clone(), post.clone()).prop_map(|(by, post)| Op::Post { by, post }),
+        (by.clone(), post.clone(), any::<bool>()).prop_map(|(by, post, paid)| Op::Like { by, post, paid }),
+        (by.clone(), post.clone()).prop_map(|(by, post)| Op::Comment { by, post }),
+        (by.clone(), post, prop_oneof![0..4u64, any::<u64>()]).prop_map(|(by, post, tokens)| Op::Boost { by, post, tokens }),
+        by.clone().prop_map(|by| Op::Mint { by }),
+        (by.clone(), 0..3u32, amount()).prop_map(|(by, index, price)| Op::Sell { by, index, price }),
+        (by.clone(), 0..3usize, 0..3u32).prop_map(|(by, seller, index)| Op::Buy { by, seller, index }),
+        (by.clone(), amount()).prop_map(|(by, amount)| Op::Stake { by, amount }),
+        (by, amount()).prop_map(|(by, amount)| Op::Unstake { by, amount }),
+    ]
+}
+
+impl Op {
+    fn signer(&self) -> usize {
+        match self {
+            Op::Post { by, .. } | Op::Like { by, .. } | Op::Comment { by, .. } | Op::Boost { by, .. } => *by,
+            Op::Mint { by } | Op::Sell { by, .. } | Op::Buy { by, .. } | Op::Stake { by, .. } | Op::Unstake { by, .. } => *by,
+        }
+    }
+
+    fn action(&self, accounts: &[Account]) -> Action {
+        let post_id = |post: &u8| format!("post{}", post);
+        match self {
+            Op::Post { by, post } => Action::PostContent { player: accounts[*by].id.clone(), post_id: post_id(post), caption: "gm".to_string() },
+            Op::Like { by, post, paid } => Action::LikePost { player: accounts[*by].id.clone(), post_id: post_id(post), paid: *paid },
+            Op::Comment { by, post } => Action::CommentPost { player: accounts[*by].id.clone(), post_id: post_id(post), comment: "nice".to_string() },
+            Op::Boost { by, post, tokens } => Action::BoostPost { player: accounts[*by].id.clone(), post_id: post_id(post), tokens: *tokens },
+            Op::Mint { by } => Action::MintNft { player: accounts[*by].id.clone(), name: "sword".to_string(), value: 1 },
+            Op::Sell { by, index, price } => Action::SellNft { seller: accounts[*by].id.clone(), nft_index: *index, price: *price },
+            Op::Buy { by, seller, index } => Action::BuyNft { buyer: accounts[*by].id.clone(), seller: accounts[*seller].id.clone(), nft_index: *index },
+            Op::Stake { by, amount } => Action::Stake { player: accounts[*by].id.clone(), amount: *amount },
+            Op::Unstake { by, amount } => Action::Unstake { player: accounts[*by].id.clone(), amount: *amount },
+        }
+    }
+}
+
+/// Every account's MTOSHI balance, stake and creator rewards counted towards
+/// the daily cap, and the platform and community pools.
+fn snapshot(blockchain: &mut Blockchain, accounts: &[Account]) -> (Vec<(u64, u64, Amount)>, (u64, u64)) {
+    let holdings = accounts
+        .iter()
+        .map(|account| {
+            let balance = match blockchain.process_action(Action::GetMtoshiBalance { player: account.id.clone() }) {
+                ActionResult::MtoshiBalance { balance, .. } => balance,
+                other => panic!("unexpected result: {:?}", other),
+            };
+            let stake = match blockchain.process_action(Action::GetStake { player: account.id.clone() }) {
+                ActionResult::StakeInfo { stake, .. } => stake,
+                other => panic!("unexpected result: {:?}", other),
+            };
+            (balance, stake, blockchain.daily_reward(&account.id))
+        })
+        .collect();
+    let pools = match blockchain.process_action(Action::GetPools) {
+        ActionResult::Pools { platform_pool, community_pool } => (platform_pool, community_pool),
+        other => panic!("unexpected result: {:?}", other),
+    };
+    (holdings, pools)
+}
+
+proptest! {
+    // Every action is mined into a block of its own, so cases are slow.
+    #![proptest_config(ProptestConfig::with_cases(12))]
+
+    /// Random actions, including absurd amounts and a balance close to
+    /// `u64::MAX`, never panic, and an action that fails leaves every balance,
+    /// stake, daily reward and pool as it was apart from the fee its signer
+    /// paid. Each
+    /// action gets a block of its own from a miner outside the accounts.
+    #[test]
+    fn failed_actions_only_cost_their_fee(ops in prop::collection::vec(op(), 1..40)) {
+        let accounts: Vec<Account> = (0..3).map(|_| Account::new()).collect();
+        let miner = Account::new();
+        let mut blockchain = Blockchain::new();
+        blockchain.allocate_mtoshi(&accounts[0].id, 100 * DTOSHI).unwrap();
+        blockchain.allocate_mtoshi(&accounts[1].id, 5 * DTOSHI).unwrap();
+        blockchain.allocate_mtoshi(&accounts[2].id, u64::MAX - DTOSHI / 2).unwrap();
+        blockchain.allocate_mtoshi(&miner.id, DTOSHI).unwrap();
+
+        for op in ops {
+            let signer = op.signer();
+            let (holdings, (platform_pool, community_pool)) = snapshot(&mut blockchain, &accounts);
+            let signed = accounts[signer].sign(&blockchain, op.action(&accounts));
+            let (hash, fee) = (signed.hash(), signed.fee());
+            let queued = blockchain.process_signed_action(signed).is_ok();
+            mine(&mut blockchain, &miner);
+            let result = match blockchain.process_action(Action::GetReceipt { hash }) {
+                ActionResult::Receipt { result, .. } => Some(*result),
+                _ => None,
+            };
+            prop_assert_eq!(result.is_some(), queued);
+            let after = snapshot(&mut blockchain, &accounts);
+            match result {
+                None => prop_assert_eq!(after, (holdings, (platform_pool, community_pool))),
+                Some(ActionResult::Error { .. }) => {
+                    let mut expected = holdings;
+                    expected[signer].0 -= fee;
+                    prop_assert_eq!(after, (expected, (platform_pool + fee / 10, community_pool + fee / 10)));
+                }
+                Some(_) => {}
+            }
+        }
+    }
+}
+
+#[test]
+fn a_reward_that_cannot_be_credited_does_not_count_towards_the_cap() {
+    let accounts = [Account::new(), Account::new(), Account::new()];
+    let [creator, fan, miner] = &accounts;
+    let mut blockchain = Blockchain::new();
+    for account in &accounts {
+        blockchain.allocate_mtoshi(&account.id, 10 * DTOSHI).unwrap();
+    }
+    let post = Action::PostContent { player: creator.id.clone(), post_id: "post0".to_string(), caption: "gm".to_string() };
+    assert!(!matches!(creator.execute(&mut blockchain, miner, post), ActionResult::Error { .. }));
+
+    // Leave the creator half a DTOSHI short of `u64::MAX`, so the like's
+    // 1 DTOSHI reward cannot be credited.
+    let (holdings, _) = snapshot(&mut blockchain, &accounts);
+    blockchain.allocate_mtoshi(&creator.id, u64::MAX - holdings[0].0 - DTOSHI / 2).unwrap();
+    let before = snapshot(&mut blockchain, &accounts);
+
+    let like = Action::LikePost { player: fan.id.clone(), post_id: "post0".to_string(), paid: false };
+    let result = fan.execute(&mut blockchain, miner, like);
+    assert!(matches!(result, ActionResult::Error { error: GameError::Overflow { .. } }), "unexpected result: {:?}", result);
+    assert_eq!(blockchain.daily_reward(&creator.id), Amount::ZERO);
+    let after = snapshot(&mut blockchain, &accounts);
+    assert_eq!(after.0[0], before.0[0], "the creator is left as it was");
+    assert!(after.0[1].0 < before.0[1].0, "the fan still pays the fee");
+}
@@ -0,0 +1,50 @@
+use shared::chain::{Chain, GENESIS_TIMESTAMP};
+use shared::merkle;
+use shared::runtime::catena_node::{meets_difficulty, Block};
+use shared::runtime::MAX_NONCE_ATTEMPTS;
+
+const DIFFICULTY: u64 = 16;
+
+fn mined_block() -> Block {
+    let genesis = Chain::genesis_block(DIFFICULTY);
+    let mut block = Block::new(1, GENESIS_TIMESTAMP + 10, genesis.hash, vec![], vec![], merkle::empty_root(), DIFFICULTY);
+    assert!(block.mine(MAX_NONCE_ATTEMPTS));
+    block
+}
+
+#[test]
+fn mined_blocks_meet_their_difficulty() {
+    let block = mined_block();
+    block.verify().unwrap();
+    assert_eq!(block.hash, block.compute_hash());
+    assert!(meets_difficulty(&block.hash, DIFFICULTY));
+}
+
+#[test]
+fn tampered_blocks_fail_verification() {
+    let block = mined_block();
+
+    let mut wrong_hash = block.clone();
+    wrong_hash.hash = "00".repeat(32);
+    let error = wrong_hash.verify().unwrap_err();
+    assert!(error.contains("hash mismatch"), "unexpected error: {}", error);
+
+    // Any header field is covered by the hash.
+    let mut wrong_timestamp = block.clone();
+    wrong_timestamp.timestamp += 1;
+    assert!(wrong_timestamp.verify().unwrap_err().contains("hash mismatch"));
+    let mut wrong_nonce = block.clone();
+    wrong_nonce.nonce += 1;
+    assert!(wrong_nonce.verify().unwrap_err().contains("hash mismatch"));
+
+    let mut wrong_root = block.clone();
+    wrong_root.tx_root = "00".repeat(32);
+    assert!(wrong_root.verify().unwrap_err().contains("tx_root mismatch"));
+
+    // A correctly hashed header that was never mined.
+    let mut unmined = block;
+    unmined.difficulty = u64::MAX;
+    unmined.hash = unmined.compute_hash();
+    let error = unmined.verify().unwrap_err();
+    assert!(error.contains("does not meet difficulty"), "unexpected error: {}", error);
+}
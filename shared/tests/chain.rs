@@ -0,0 +1,114 @@
+use shared::chain::{Chain, GENESIS_TIMESTAMP, MAX_FUTURE_DRIFT_SECS, MAX_RETARGET_FACTOR, RETARGET_INTERVAL, TARGET_BLOCK_TIME_SECS};
+use shared::merkle;
+use shared::runtime::catena_node::Block;
+use shared::runtime::MAX_NONCE_ATTEMPTS;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DIFFICULTY: u64 = 16;
+
+/// A mined block on top of the chain's head, `elapsed` seconds after it.
+fn next_block(chain: &Chain, elapsed: u64) -> Block {
+    let head = chain.head();
+    let mut block = Block::new(head.index + 1, head.timestamp + elapsed, head.hash.clone(), vec![], vec![], merkle::empty_root(), chain.next_difficulty());
+    assert!(block.mine(MAX_NONCE_ATTEMPTS));
+    block
+}
+
+fn remine(mut block: Block) -> Block {
+    assert!(block.mine(MAX_NONCE_ATTEMPTS));
+    block
+}
+
+#[test]
+fn blocks_are_linked_onto_the_head() {
+    let mut chain = Chain::new(DIFFICULTY);
+    let genesis = chain.head().hash.clone();
+    let block = next_block(&chain, 10);
+    chain.validate_next(&block).unwrap();
+    chain.append(block.clone()).unwrap();
+    assert_eq!(chain.height(), 1);
+    assert_eq!(chain.head().previous_hash, genesis);
+    assert_eq!(chain.get_by_height(1).map(|block| &block.hash), Some(&block.hash));
+    assert!(chain.append(block).is_err(), "the same block is appended twice");
+}
+
+#[test]
+fn wrongly_linked_blocks_are_rejected() {
+    let mut chain = Chain::new(DIFFICULTY);
+    chain.append(next_block(&chain, 10)).unwrap();
+    let valid = next_block(&chain, 10);
+
+    let mut wrong_index = valid.clone();
+    wrong_index.index += 1;
+    let error = chain.validate_next(&remine(wrong_index)).unwrap_err();
+    assert!(error.contains("Invalid block index"), "unexpected error: {}", error);
+
+    let mut wrong_parent = valid.clone();
+    wrong_parent.previous_hash = chain.get_by_height(0).unwrap().hash.clone();
+    let error = chain.validate_next(&remine(wrong_parent)).unwrap_err();
+    assert!(error.contains("Invalid parent"), "unexpected error: {}", error);
+
+    let mut wrong_difficulty = valid.clone();
+    wrong_difficulty.difficulty = DIFFICULTY + 1;
+    let error = chain.validate_next(&remine(wrong_difficulty)).unwrap_err();
+    assert!(error.contains("Invalid difficulty"), "unexpected error: {}", error);
+
+    chain.validate_next(&valid).unwrap();
+    assert_eq!(chain.height(), 1);
+}
+
+#[test]
+fn blocks_out_of_time_are_rejected() {
+    let mut chain = Chain::new(DIFFICULTY);
+    chain.append(next_block(&chain, 10)).unwrap();
+    let valid = next_block(&chain, 0);
+    chain.validate_next(&valid).unwrap();
+
+    let mut before_parent = valid.clone();
+    before_parent.timestamp = chain.head().timestamp - 1;
+    let error = chain.validate_next(&remine(before_parent)).unwrap_err();
+    assert!(error.contains("earlier than parent"), "unexpected error: {}", error);
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let mut in_the_future = valid;
+    in_the_future.timestamp = now + MAX_FUTURE_DRIFT_SECS + 60;
+    let error = chain.validate_next(&remine(in_the_future)).unwrap_err();
+    assert!(error.contains("too far in the future"), "unexpected error: {}", error);
+}
+
+#[test]
+fn the_genesis_block_is_the_same_everywhere() {
+    let (first, second) = (Chain::new(DIFFICULTY), Chain::new(DIFFICULTY));
+    assert_eq!(first.head().hash, second.head().hash);
+    assert_eq!(first.head().timestamp, GENESIS_TIMESTAMP);
+}
+
+/// Difficulty declared by block `RETARGET_INTERVAL` when every block before
+/// it came `block_time` seconds after its parent.
+fn retargeted_difficulty(block_time: u64) -> u64 {
+    let mut chain = Chain::new(DIFFICULTY);
+    for _ in 1..RETARGET_INTERVAL {
+        assert_eq!(chain.next_difficulty(), DIFFICULTY, "difficulty only moves at retarget heights");
+        chain.append(next_block(&chain, block_time)).unwrap();
+    }
+    let difficulty = chain.next_difficulty();
+    chain.append(next_block(&chain, block_time)).unwrap();
+    assert_eq!(chain.next_difficulty(), difficulty, "the retargeted difficulty holds until the next window");
+    difficulty
+}
+
+#[test]
+fn difficulty_follows_the_observed_block_time() {
+    assert_eq!(retargeted_difficulty(TARGET_BLOCK_TIME_SECS), DIFFICULTY);
+    assert_eq!(retargeted_difficulty(2 * TARGET_BLOCK_TIME_SECS), DIFFICULTY / 2);
+    assert_eq!(retargeted_difficulty(TARGET_BLOCK_TIME_SECS / 2), DIFFICULTY * 2);
+}
+
+#[test]
+fn retargets_are_clamped() {
+    // A window mined instantly counts as a quarter of the expected time, and
+    // one far too slow as four times it.
+    let expected = (RETARGET_INTERVAL - 1) * TARGET_BLOCK_TIME_SECS;
+    assert_eq!(retargeted_difficulty(0), DIFFICULTY * expected / (expected / MAX_RETARGET_FACTOR));
+    assert_eq!(retargeted_difficulty(1_000), DIFFICULTY / MAX_RETARGET_FACTOR);
+}
@@ -0,0 +1,61 @@
+//! Fixtures shared by the integration tests. Each test crate uses only some
+//! of them.
+#![allow(dead_code)]
+
+use libp2p::identity::Keypair;
+use shared::auth::{account_id, SignedAction};
+use shared::blockchain::{Action, ActionResult, Blockchain};
+use shared::runtime::catena_node::Block;
+
+/// A player holding its own signing key.
+pub struct Account {
+    pub keypair: Keypair,
+    pub id: String,
+}
+
+impl Account {
+    pub fn new() -> Self {
+        let keypair = Keypair::generate_ed25519();
+        let id = account_id(&keypair.public());
+        Account { keypair, id }
+    }
+
+    /// Signs `action` with the account's next nonce on `blockchain`.
+    pub fn sign(&self, blockchain: &Blockchain, action: Action) -> SignedAction {
+        SignedAction::sign(&self.keypair, action, blockchain.account_nonce(&self.id))
+    }
+
+    /// Signs `action` and queues it, returning its hash.
+    pub fn submit(&self, blockchain: &mut Blockchain, action: Action) -> String {
+        let signed = self.sign(blockchain, action);
+        match blockchain.process_signed_action(signed).expect("action accepted") {
+            ActionResult::ActionQueued { hash } => hash,
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    /// Queues `action`, has `miner` mine it into a proof-of-work block and
+    /// returns its outcome.
+    pub fn execute(&self, blockchain: &mut Blockchain, miner: &Account, action: Action) -> ActionResult {
+        let hash = self.submit(blockchain, action);
+        mine(blockchain, miner);
+        receipt(blockchain, &hash)
+    }
+}
+
+/// Mines a proof-of-work block on the head, with `miner` paying for it.
+pub fn mine(blockchain: &mut Blockchain, miner: &Account) -> Block {
+    let mine = miner.sign(blockchain, Action::MineBlock { miner: miner.id.clone() });
+    match blockchain.process_signed_action(mine).unwrap() {
+        ActionResult::BlockMined { block: Some(block), .. } => block,
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+/// Outcome of the included action with hash `hash`.
+pub fn receipt(blockchain: &mut Blockchain, hash: &str) -> ActionResult {
+    match blockchain.process_action(Action::GetReceipt { hash: hash.to_string() }) {
+        ActionResult::Receipt { result, .. } => *result,
+        other => panic!("no receipt for {}: {:?}", hash, other),
+    }
+}
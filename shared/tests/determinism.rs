@@ -0,0 +1,84 @@
+mod common;
+
+use common::{mine, receipt, Account};
+use shared::auth::SignedAction;
+use shared::blockchain::{Action, ActionResult, Blockchain};
+use shared::runtime::{GameFiRuntime, Item, Player};
+
+const DTOSHI: u64 = 1_000_000_000;
+
+fn players_json(runtime: &GameFiRuntime) -> serde_json::Value {
+    serde_json::to_value(&runtime.players).unwrap()
+}
+
+#[test]
+fn runtimes_replaying_the_same_actions_agree() {
+    let mut runtimes = [GameFiRuntime::new(), GameFiRuntime::new()];
+    for runtime in runtimes.iter_mut() {
+        for id in ["alice", "bob", "carol"] {
+            let mut player = Player::new(id.to_string());
+            player.add_item(Item { name: format!("{} sword", id), value: 5 });
+            runtime.players.insert(id.to_string(), player);
+        }
+    }
+
+    for runtime in runtimes.iter_mut() {
+        for round in 0..20u64 {
+            let block_hash = format!("block-{}", round / 5);
+            runtime.seed_rng(&block_hash, &format!("action-{}", round), round % 5);
+            match round % 4 {
+                0 => runtime.simulate_player_activity(),
+                1 => runtime.record_activity("alice", 4),
+                2 => {
+                    runtime.battle("alice", "bob");
+                }
+                _ => {
+                    runtime.trade_items("bob", 0, "carol", 0);
+                }
+            }
+        }
+    }
+
+    assert_eq!(players_json(&runtimes[0]), players_json(&runtimes[1]));
+}
+
+#[test]
+fn blockchains_replaying_the_same_signed_actions_agree() {
+    let (alice, bob, miner) = (Account::new(), Account::new(), Account::new());
+    let actions = [
+        SignedAction::sign(&alice.keypair, Action::RecordActivity { player: alice.id.clone(), activity_type: 4 }, 0),
+        SignedAction::sign(&bob.keypair, Action::RecordActivity { player: bob.id.clone(), activity_type: 2 }, 0),
+        SignedAction::sign(&alice.keypair, Action::Battle { player1: alice.id.clone(), player2: bob.id.clone() }, 1).cosign(&bob.keypair),
+        SignedAction::sign(&bob.keypair, Action::Battle { player1: bob.id.clone(), player2: alice.id.clone() }, 1).cosign(&alice.keypair),
+        SignedAction::sign(&alice.keypair, Action::SimulateActivity, 2),
+        SignedAction::sign(&bob.keypair, Action::DistributeMtoshiRewards, 2),
+    ];
+    let node = || {
+        let mut blockchain = Blockchain::new();
+        for account in [&alice, &bob, &miner] {
+            blockchain.allocate_mtoshi(&account.id, DTOSHI).unwrap();
+        }
+        blockchain
+    };
+
+    // One node mines the actions into a block and the other imports it.
+    let (mut producer, mut follower) = (node(), node());
+    for signed in actions.iter().cloned() {
+        producer.submit_action(signed).unwrap();
+    }
+    let block = mine(&mut producer, &miner);
+    assert_eq!(block.actions.len(), actions.len() + 1);
+    follower.import_block(block).unwrap();
+
+    let outcomes = |blockchain: &mut Blockchain| -> Vec<String> {
+        actions
+            .iter()
+            .map(|signed| match receipt(blockchain, &signed.hash()) {
+                ActionResult::BattleResult { winner, .. } => winner,
+                other => format!("{:?}", other),
+            })
+            .collect()
+    };
+    assert_eq!(outcomes(&mut producer), outcomes(&mut follower));
+    assert_eq!(serde_json::to_value(&producer).unwrap(), serde_json::to_value(&follower).unwrap());
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use common::Account;
+use shared::auth::AuthError;
+use shared::blockchain::{Action, ActionResult, Blockchain, BASE_FEE_STANDARD};
+use shared::error::GameError;
+use shared::mempool::MempoolError;
+
+const DTOSHI: u64 = 1_000_000_000;
+
+fn error_of(result: ActionResult) -> GameError {
+    match result {
+        ActionResult::Error { error } => error,
+        other => panic!("expected an error, got {:?}", other),
+    }
+}
+
+#[test]
+fn failed_actions_report_typed_errors() {
+    let mut blockchain = Blockchain::new();
+    let (player, miner) = (Account::new(), Account::new());
+    blockchain.allocate_mtoshi(&player.id, 5 * DTOSHI).unwrap();
+    blockchain.allocate_mtoshi(&miner.id, 100 * DTOSHI).unwrap();
+
+    let post = Action::PostContent { player: player.id.clone(), post_id: "post1".to_string(), caption: "hello".to_string() };
+    assert!(!matches!(player.execute(&mut blockchain, &miner, post), ActionResult::Error { .. }));
+    let like = Action::LikePost { player: player.id.clone(), post_id: "post1".to_string(), paid: false };
+    assert!(!matches!(player.execute(&mut blockchain, &miner, like.clone()), ActionResult::Error { .. }));
+
+    let error = error_of(player.execute(&mut blockchain, &miner, like));
+    assert_eq!(error, GameError::AlreadyLiked { kind: "Post".to_string(), id: "post1".to_string() });
+    assert_eq!(error.code(), 1005);
+
+    let missing = Action::LikePost { player: player.id.clone(), post_id: "post2".to_string(), paid: false };
+    let error = error_of(player.execute(&mut blockchain, &miner, missing));
+    assert_eq!(error, GameError::not_found("Post", "post2"));
+    assert_eq!(error.code(), 1004);
+
+    let available = match blockchain.process_action(Action::GetMtoshiBalance { player: player.id.clone() }) {
+        // The mint's own fee is charged before it runs.
+        ActionResult::MtoshiBalance { balance, .. } => balance - BASE_FEE_STANDARD,
+        other => panic!("unexpected result: {:?}", other),
+    };
+    let mint = Action::MintNft { player: player.id.clone(), name: "sword".to_string(), value: 1 };
+    let error = error_of(player.execute(&mut blockchain, &miner, mint));
+    assert_eq!(error, GameError::InsufficientBalance { needed: 10 * DTOSHI, available, token: "MTOSHI".to_string() });
+    assert_eq!(error.code(), 1003);
+
+    let unsigned = blockchain.process_action(Action::Stake { player: player.id.clone(), amount: DTOSHI });
+    assert!(matches!(error_of(unsigned), GameError::Unauthorized { .. }));
+}
+
+#[test]
+fn auth_and_mempool_errors_map_to_stable_codes() {
+    assert_eq!(GameError::from(AuthError::InvalidSignature).code(), 1001);
+    assert_eq!(GameError::from(AuthError::InvalidNonce { expected: 2, got: 1 }), GameError::InvalidNonce { expected: 2, got: 1 });
+    assert_eq!(
+        GameError::from(MempoolError::Auth(AuthError::InsufficientFee { needed: 10, available: 3 })),
+        GameError::InsufficientBalance { needed: 10, available: 3, token: "MTOSHI".to_string() }
+    );
+    assert_eq!(GameError::from(MempoolError::Full).code(), 1008);
+
+    let rpc = serde_json::to_value(GameError::not_found("Post", "post2").to_rpc_error()).unwrap();
+    assert_eq!(rpc["code"], 1004);
+    assert_eq!(rpc["message"], "Post post2 not found");
+    assert_eq!(rpc["data"]["NotFound"]["id"], "post2");
+}
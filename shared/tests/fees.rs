@@ -0,0 +1,127 @@
+mod common;
+
+use common::{mine, receipt, Account};
+use shared::auth::SignedAction;
+use shared::blockchain::{Action, ActionResult, Blockchain, BASE_FEE_STANDARD, FAUCET_AMOUNT, FAUCET_COOLDOWN_BLOCKS};
+use shared::chain::GENESIS_TIMESTAMP;
+use shared::consensus::{Consensus, ValidatorSet};
+use shared::error::GameError;
+use shared::ledger::{MTOSHI, SOLUTIO};
+use shared::mempool::MempoolError;
+
+const DTOSHI: u64 = 1_000_000_000;
+const SLOT_SECS: u64 = 5;
+
+fn pools(blockchain: &mut Blockchain) -> (u64, u64) {
+    match blockchain.process_action(Action::GetPools) {
+        ActionResult::Pools { platform_pool, community_pool } => (platform_pool, community_pool),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+fn request(player: &Account, token: &str) -> Action {
+    Action::RequestTokens { player: player.id.clone(), token: token.to_string() }
+}
+
+#[test]
+fn fees_are_split_between_producer_and_pools() {
+    let mut blockchain = Blockchain::new();
+    let (player, miner) = (Account::new(), Account::new());
+    blockchain.allocate_mtoshi(&player.id, DTOSHI).unwrap();
+    blockchain.allocate_mtoshi(&miner.id, 100 * DTOSHI).unwrap();
+
+    let tip = 500_000;
+    let action = Action::RecordActivity { player: player.id.clone(), activity_type: 1 };
+    blockchain.submit_action(SignedAction::sign_with_tip(&player.keypair, action, 0, tip)).unwrap();
+    let block = mine(&mut blockchain, &miner);
+
+    // The producer's own block action is free.
+    let fees = BASE_FEE_STANDARD + tip;
+    assert_eq!(blockchain.ledger().balance(&player.id, MTOSHI).units(), DTOSHI - BASE_FEE_STANDARD - tip);
+    assert_eq!(pools(&mut blockchain), (fees / 10, fees / 10));
+    let payout = block.transactions.iter().find(|transaction| transaction.sender == "fees").expect("fee payout");
+    assert_eq!(payout.receiver, miner.id);
+    assert_eq!(payout.amount, (fees * 8 / 10) as u128);
+}
+
+#[test]
+fn actions_that_cannot_pay_their_fee_are_refused() {
+    let mut blockchain = Blockchain::new();
+    let (player, spender, miner) = (Account::new(), Account::new(), Account::new());
+    blockchain.allocate_mtoshi(&player.id, BASE_FEE_STANDARD - 1).unwrap();
+    blockchain.allocate_mtoshi(&miner.id, 100 * DTOSHI).unwrap();
+
+    let post = Action::PostContent { player: player.id.clone(), post_id: "post1".to_string(), caption: "hello".to_string() };
+    assert_eq!(
+        blockchain.process_signed_action(player.sign(&blockchain, post)).unwrap_err(),
+        MempoolError::InsufficientFee { needed: BASE_FEE_STANDARD, available: BASE_FEE_STANDARD - 1 }
+    );
+    assert!(blockchain.mempool().is_empty());
+
+    // Enough for both fees when queued, but the mint spends the rest first,
+    // so the producer leaves the second action waiting in the pool.
+    blockchain.allocate_mtoshi(&spender.id, 10 * DTOSHI + BASE_FEE_STANDARD + BASE_FEE_STANDARD / 2).unwrap();
+    let mint = SignedAction::sign(&spender.keypair, Action::MintNft { player: spender.id.clone(), name: "sword".to_string(), value: 1 }, 0);
+    let activity = SignedAction::sign(&spender.keypair, Action::RecordActivity { player: spender.id.clone(), activity_type: 1 }, 1);
+    let (mint, activity) = (blockchain.submit_action(mint).unwrap(), blockchain.submit_action(activity).unwrap());
+    let block = mine(&mut blockchain, &miner);
+    assert!(matches!(receipt(&mut blockchain, &mint), ActionResult::NftMinted { .. }));
+    assert!(block.actions.iter().all(|signed| signed.hash() != activity));
+    assert!(blockchain.mempool().contains(&activity));
+    assert_eq!(blockchain.account_nonce(&spender.id), 1);
+
+    blockchain.allocate_mtoshi(&spender.id, BASE_FEE_STANDARD).unwrap();
+    mine(&mut blockchain, &miner);
+    assert!(matches!(receipt(&mut blockchain, &activity), ActionResult::ActivityRecorded { .. }));
+}
+
+#[test]
+fn the_faucet_is_rate_limited_per_token() {
+    // Authority blocks are cheap to produce, unlike a hundred retargeted
+    // proof-of-work blocks.
+    let (player, validator) = (Account::new(), Account::new());
+    let validators = ValidatorSet::new(vec![validator.id.clone()], SLOT_SECS);
+    let mut blockchain = Blockchain::with_consensus(Consensus::ProofOfAuthority(validators));
+    let mut slot = 0;
+    let mut produce = |blockchain: &mut Blockchain| {
+        slot += 1;
+        blockchain.propose_block(&validator.keypair, GENESIS_TIMESTAMP + slot * SLOT_SECS).expect("validator's turn")
+    };
+
+    // Both requests are queued before either runs, so only the first pays out.
+    let first = player.submit(&mut blockchain, request(&player, MTOSHI));
+    let second = blockchain.submit_action(SignedAction::sign(&player.keypair, request(&player, MTOSHI), 1)).unwrap();
+    let other_token = blockchain.submit_action(SignedAction::sign(&player.keypair, request(&player, SOLUTIO), 2)).unwrap();
+    let block = produce(&mut blockchain);
+    assert!(matches!(receipt(&mut blockchain, &first), ActionResult::TokensRequested { .. }));
+    assert!(matches!(receipt(&mut blockchain, &second), ActionResult::Error { error: GameError::NotAllowed { .. } }));
+    assert!(matches!(receipt(&mut blockchain, &other_token), ActionResult::TokensRequested { .. }));
+    assert_eq!(blockchain.ledger().balance(&player.id, MTOSHI), FAUCET_AMOUNT);
+
+    let release_height = block.index + FAUCET_COOLDOWN_BLOCKS;
+    let again = player.sign(&blockchain, request(&player, MTOSHI));
+    assert_eq!(blockchain.submit_action(again.clone()).unwrap_err(), MempoolError::RateLimited { release_height });
+    while blockchain.chain().height() + 1 < release_height {
+        produce(&mut blockchain);
+    }
+    let hash = blockchain.submit_action(again).unwrap();
+    produce(&mut blockchain);
+    assert!(matches!(receipt(&mut blockchain, &hash), ActionResult::TokensRequested { .. }));
+    assert_eq!(blockchain.ledger().balance(&player.id, MTOSHI).units(), 2 * FAUCET_AMOUNT.units());
+}
+
+#[test]
+fn faucet_funds_pay_for_actions_on_a_fresh_chain() {
+    // Neither account holds anything before the faucet pays out.
+    let mut blockchain = Blockchain::new();
+    let (player, miner) = (Account::new(), Account::new());
+    let faucet = player.submit(&mut blockchain, request(&player, MTOSHI));
+    mine(&mut blockchain, &miner);
+    assert!(matches!(receipt(&mut blockchain, &faucet), ActionResult::TokensRequested { .. }));
+
+    let hash = player.submit(&mut blockchain, Action::RecordActivity { player: player.id.clone(), activity_type: 1 });
+    let block = mine(&mut blockchain, &miner);
+    assert!(block.actions.iter().any(|signed| signed.hash() == hash));
+    assert!(!matches!(receipt(&mut blockchain, &hash), ActionResult::Error { .. }));
+    assert_eq!(blockchain.ledger().balance(&player.id, MTOSHI).units(), FAUCET_AMOUNT.units() - BASE_FEE_STANDARD);
+}
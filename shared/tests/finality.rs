@@ -0,0 +1,288 @@
+use libp2p::gossipsub;
+use libp2p::identity::Keypair;
+use libp2p::swarm::SwarmEvent;
+use shared::auth::account_id;
+use shared::blockchain::{Action, ActionResult, Blockchain};
+use shared::chain::GENESIS_TIMESTAMP;
+use shared::consensus::{Consensus, ValidatorSet};
+use shared::finality::{finality_topic, FinalityGadget, Progress, Vote, VoteKind, ROUND_TIMEOUT_SECS};
+use shared::network::{blocks_topic, setup_network, CustomEvent, Network, NetworkConfig};
+use shared::runtime::catena_node::Block;
+use std::collections::{HashSet, VecDeque};
+use std::time::Duration;
+
+const SLOT_SECS: u64 = 5;
+
+struct Node {
+    keypair: Keypair,
+    blockchain: Blockchain,
+    gadget: FinalityGadget,
+    online: bool,
+}
+
+/// In-process stand-in for the gossipsub finality topic: every vote is
+/// delivered to every online node until no node has anything left to say.
+struct Testnet {
+    nodes: Vec<Node>,
+    consensus: Consensus,
+}
+
+impl Testnet {
+    fn new(size: usize) -> Self {
+        let keys: Vec<Keypair> = (0..size).map(|_| Keypair::generate_ed25519()).collect();
+        let ids: Vec<String> = keys.iter().map(|key| account_id(&key.public())).collect();
+        let consensus = Consensus::ProofOfAuthority(ValidatorSet::new(ids.clone(), SLOT_SECS));
+        let nodes = keys
+            .into_iter()
+            .map(|keypair| Node {
+                blockchain: Blockchain::with_consensus(consensus.clone()),
+                gadget: FinalityGadget::new(ids.clone(), Some(keypair.clone())),
+                keypair,
+                online: true,
+            })
+            .collect();
+        Testnet { nodes, consensus }
+    }
+
+    /// Lets the validator of `slot` propose, hands the block to every online
+    /// node and runs voting to completion.
+    fn produce(&mut self, slot: u64) -> Block {
+        let now = GENESIS_TIMESTAMP + slot * SLOT_SECS + 1;
+        let proposer = (slot % self.nodes.len() as u64) as usize;
+        let node = &mut self.nodes[proposer];
+        let block = node.blockchain.propose_block(&node.keypair, now).expect("proposer's turn");
+
+        let mut queue = VecDeque::new();
+        for (i, node) in self.nodes.iter_mut().enumerate().filter(|(_, node)| node.online) {
+            if i != proposer {
+                node.blockchain.import_block(block.clone()).unwrap();
+            }
+            let progress = node.gadget.on_block(block.index, &block.hash);
+            Self::apply(node, progress, &mut queue);
+        }
+        while let Some(vote) = queue.pop_front() {
+            for node in self.nodes.iter_mut().filter(|node| node.online) {
+                let progress = node.gadget.on_vote(vote.clone()).unwrap();
+                Self::apply(node, progress, &mut queue);
+            }
+        }
+        block
+    }
+
+    fn apply(node: &mut Node, progress: Progress, queue: &mut VecDeque<Vote>) {
+        queue.extend(progress.votes);
+        if let Some((_, hash)) = progress.finalized {
+            node.blockchain.finalize(&hash).unwrap();
+        }
+    }
+}
+
+fn finalized_head(blockchain: &mut Blockchain) -> (u64, String) {
+    match blockchain.process_action(Action::GetFinalizedHead) {
+        ActionResult::FinalizedHead { height, hash } => (height, hash),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn blocks_with_a_precommit_quorum_become_final() {
+    let mut testnet = Testnet::new(4);
+    let first = testnet.produce(1);
+    for node in &mut testnet.nodes {
+        assert_eq!(finalized_head(&mut node.blockchain), (1, first.hash.clone()));
+    }
+    let second = testnet.produce(2);
+    for node in &mut testnet.nodes {
+        assert_eq!(finalized_head(&mut node.blockchain), (2, second.hash.clone()));
+    }
+}
+
+#[test]
+fn no_finality_without_two_thirds_online() {
+    let mut testnet = Testnet::new(4);
+    testnet.nodes[2].online = false;
+    testnet.nodes[3].online = false;
+    testnet.produce(1);
+    assert_eq!(finalized_head(&mut testnet.nodes[0].blockchain).0, 0);
+    assert_eq!(testnet.nodes[0].blockchain.chain().height(), 1);
+}
+
+#[test]
+fn final_blocks_are_never_reorganized() {
+    let mut testnet = Testnet::new(4);
+    let finalized = testnet.produce(1);
+
+    // A longer branch built by validators that never saw the final block.
+    let mut rival = Blockchain::with_consensus(testnet.consensus.clone());
+    let mut branch = vec![];
+    for slot in 2..=3 {
+        let keypair = testnet.nodes[slot as usize].keypair.clone();
+        branch.push(rival.propose_block(&keypair, GENESIS_TIMESTAMP + slot * SLOT_SECS + 1).unwrap());
+    }
+
+    let node = &mut testnet.nodes[0];
+    for block in branch {
+        assert!(node.blockchain.import_block(block).is_err());
+    }
+    assert_eq!(node.blockchain.chain().head().hash, finalized.hash);
+    assert_eq!(finalized_head(&mut node.blockchain), (1, finalized.hash));
+}
+
+#[test]
+fn conflicting_votes_from_one_validator_are_not_counted() {
+    let keypair = Keypair::generate_ed25519();
+    let validators = vec![account_id(&keypair.public())];
+    let mut gadget = FinalityGadget::new(validators, None);
+    gadget.on_vote(Vote::sign(&keypair, VoteKind::Prevote, 1, 0, "a")).unwrap();
+    assert!(gadget.on_vote(Vote::sign(&keypair, VoteKind::Prevote, 1, 0, "b")).is_err());
+    assert!(gadget.on_vote(Vote::sign(&Keypair::generate_ed25519(), VoteKind::Prevote, 1, 0, "a")).is_err());
+    // A later round is a fresh vote.
+    gadget.on_vote(Vote::sign(&keypair, VoteKind::Prevote, 1, 1, "b")).unwrap();
+}
+
+/// Delivers every queued vote to every gadget until none has anything left
+/// to say, returning the blocks they finalized.
+fn deliver(gadgets: &mut [FinalityGadget], mut queue: VecDeque<Vote>) -> Vec<Option<(u64, String)>> {
+    let mut finalized = vec![None; gadgets.len()];
+    while let Some(vote) = queue.pop_front() {
+        for (gadget, finalized) in gadgets.iter_mut().zip(finalized.iter_mut()) {
+            let progress = gadget.on_vote(vote.clone()).unwrap();
+            queue.extend(progress.votes);
+            if progress.finalized.is_some() {
+                *finalized = progress.finalized;
+            }
+        }
+    }
+    finalized
+}
+
+fn validator_gadgets(size: usize) -> (Vec<Keypair>, Vec<FinalityGadget>) {
+    let keys: Vec<Keypair> = (0..size).map(|_| Keypair::generate_ed25519()).collect();
+    let ids: Vec<String> = keys.iter().map(|key| account_id(&key.public())).collect();
+    let gadgets = keys.iter().map(|key| FinalityGadget::new(ids.clone(), Some(key.clone()))).collect();
+    (keys, gadgets)
+}
+
+#[test]
+fn split_votes_are_resolved_in_a_later_round() {
+    let (_, mut gadgets) = validator_gadgets(4);
+    let (low, high) = ("a".repeat(64), "b".repeat(64));
+
+    // Half the validators see each of two competing blocks first.
+    let mut queue = VecDeque::new();
+    for (i, gadget) in gadgets.iter_mut().enumerate() {
+        let (first, second) = if i < 2 { (&high, &low) } else { (&low, &high) };
+        queue.extend(gadget.on_block(1, first).votes);
+        queue.extend(gadget.on_block(1, second).votes);
+    }
+    assert!(deliver(&mut gadgets, queue).iter().all(Option::is_none));
+
+    // Nothing happens before the round times out.
+    let start = 1_000;
+    let mut queue = VecDeque::new();
+    for gadget in gadgets.iter_mut() {
+        assert!(gadget.tick(start).votes.is_empty());
+        assert!(gadget.tick(start + ROUND_TIMEOUT_SECS - 1).votes.is_empty());
+        let votes = gadget.tick(start + ROUND_TIMEOUT_SECS).votes;
+        assert_eq!(gadget.round(1), 1);
+        assert_eq!((votes[0].round, &votes[0].block_hash), (1, &low));
+        queue.extend(votes);
+    }
+    assert!(deliver(&mut gadgets, queue).into_iter().all(|finalized| finalized == Some((1, low.clone()))));
+}
+
+#[test]
+fn validators_stay_locked_on_what_they_precommitted() {
+    let (keys, mut gadgets) = validator_gadgets(4);
+    let mut gadget = gadgets.remove(0);
+    let (locked, other) = ("b".repeat(64), "a".repeat(64));
+
+    // Two prevotes from others complete a quorum with our own, so we precommit.
+    gadget.on_block(1, &locked);
+    for key in &keys[1..3] {
+        gadget.on_vote(Vote::sign(key, VoteKind::Prevote, 1, 0, &locked)).unwrap();
+    }
+    gadget.on_block(1, &other);
+
+    // The next round prevotes the locked block, though the other hash is lower.
+    gadget.tick(0);
+    let votes = gadget.tick(ROUND_TIMEOUT_SECS).votes;
+    assert_eq!((votes[0].kind, votes[0].round, &votes[0].block_hash), (VoteKind::Prevote, 1, &locked));
+
+    // A quorum for the other block in a later round releases the lock.
+    let mut precommits = vec![];
+    for key in &keys[1..] {
+        precommits.extend(gadget.on_vote(Vote::sign(key, VoteKind::Prevote, 1, 1, &other)).unwrap().votes);
+    }
+    assert_eq!(precommits.len(), 1);
+    assert_eq!((precommits[0].kind, precommits[0].round, &precommits[0].block_hash), (VoteKind::Precommit, 1, &other));
+}
+
+struct Peer {
+    network: Network,
+    blockchain: Blockchain,
+    gadget: FinalityGadget,
+}
+
+/// Waits for the next event on either peer, returning which one it came from.
+async fn next_event(peers: &mut [Peer; 2]) -> (usize, SwarmEvent<CustomEvent>) {
+    let [first, second] = peers;
+    tokio::select! {
+        event = first.network.next_event() => (0, event),
+        event = second.network.next_event() => (1, event),
+    }
+}
+
+#[tokio::test]
+async fn votes_gossiped_between_nodes_finalize_blocks() {
+    let keys = [Keypair::generate_ed25519(), Keypair::generate_ed25519()];
+    let ids: Vec<String> = keys.iter().map(|key| account_id(&key.public())).collect();
+    let consensus = Consensus::ProofOfAuthority(ValidatorSet::new(ids.clone(), SLOT_SECS));
+    let config = |dial_addrs| NetworkConfig { listen_addr: "/ip4/127.0.0.1/tcp/0".to_string(), dial_addrs, key_file: None, mdns: false };
+
+    let mut listener = setup_network(config(vec![])).await.unwrap();
+    let addr = loop {
+        if let SwarmEvent::NewListenAddr { address, .. } = listener.next_event().await {
+            break address.to_string();
+        }
+    };
+    let dialer = setup_network(config(vec![addr])).await.unwrap();
+    let mut peers = [(listener, &keys[0]), (dialer, &keys[1])].map(|(network, key)| Peer {
+        network,
+        blockchain: Blockchain::with_consensus(consensus.clone()),
+        gadget: FinalityGadget::new(ids.clone(), Some(key.clone())),
+    });
+    for peer in peers.iter_mut() {
+        peer.network.join_gossip();
+    }
+
+    // Gossip only reaches a node once the other knows it subscribed.
+    let topics = [blocks_topic(), finality_topic()].map(|topic| topic.hash());
+    let mut subscribed = [HashSet::new(), HashSet::new()];
+    tokio::time::timeout(Duration::from_secs(20), async {
+        while subscribed.iter().any(|seen| !topics.iter().all(|topic| seen.contains(topic))) {
+            if let (i, SwarmEvent::Behaviour(CustomEvent::Gossipsub(gossipsub::Event::Subscribed { topic, .. }))) = next_event(&mut peers).await {
+                subscribed[i].insert(topic);
+            }
+        }
+    })
+    .await
+    .expect("nodes subscribe to each other");
+
+    // Slot 1 belongs to the second validator. The first node imports its
+    // block from gossip and votes on it there.
+    let block = peers[1].blockchain.propose_block(&keys[1], GENESIS_TIMESTAMP + SLOT_SECS + 1).unwrap();
+    let producer = &mut peers[1];
+    producer.network.publish_block(&block).unwrap();
+    let progress = producer.gadget.on_block(block.index, &block.hash);
+    producer.network.apply_finality(progress, &mut producer.blockchain);
+    tokio::time::timeout(Duration::from_secs(20), async {
+        while peers.iter().any(|peer| peer.blockchain.chain().finalized_head().hash != block.hash) {
+            let (i, event) = next_event(&mut peers).await;
+            let peer = &mut peers[i];
+            peer.network.handle_gossip(&event, &mut peer.blockchain, &mut peer.gadget);
+        }
+    })
+    .await
+    .expect("both nodes finalize the block");
+}
@@ -0,0 +1,117 @@
+mod common;
+
+use common::{mine, Account};
+use shared::auth::SignedAction;
+use shared::blockchain::{Action, ActionResult, Blockchain};
+use shared::chain::GENESIS_TIMESTAMP;
+use shared::consensus::{Consensus, ValidatorSet};
+use shared::runtime::MAX_NONCE_ATTEMPTS;
+
+const DTOSHI: u64 = 1_000_000_000;
+const SLOT_SECS: u64 = 5;
+
+fn node(accounts: &[&Account]) -> Blockchain {
+    let mut blockchain = Blockchain::new();
+    for account in accounts {
+        blockchain.allocate_mtoshi(&account.id, 100 * DTOSHI).unwrap();
+    }
+    blockchain
+}
+
+fn inventory_len(blockchain: &mut Blockchain, player: &str) -> usize {
+    let proof = match blockchain.process_action(Action::GetAccountProof { player: player.to_string(), height: None }) {
+        ActionResult::AccountProof { proof } => proof,
+        other => panic!("unexpected result: {:?}", other),
+    };
+    proof.player.value.inventory.len()
+}
+
+#[test]
+fn heavier_branch_wins_and_orphaned_actions_return_to_the_pool() {
+    let (miner_a, miner_b, player) = (Account::new(), Account::new(), Account::new());
+    let accounts = [&miner_a, &miner_b, &player];
+    let (mut node_a, mut node_b) = (node(&accounts), node(&accounts));
+
+    let mint = SignedAction::sign(&player.keypair, Action::MintNft {
+        player: player.id.clone(),
+        name: "Gem".to_string(),
+        value: 100,
+    }, 0);
+    let mint_hash = node_a.submit_action(mint).unwrap();
+    let a1 = mine(&mut node_a, &miner_a);
+    assert_eq!(inventory_len(&mut node_a, &player.id), 1);
+
+    let b1 = mine(&mut node_b, &miner_b);
+    let b2 = mine(&mut node_b, &miner_b);
+
+    // Equal work: the first block seen stays at the head.
+    node_a.import_block(b1).unwrap();
+    assert_eq!(node_a.chain().head().hash, a1.hash);
+
+    node_a.import_block(b2.clone()).unwrap();
+    assert_eq!(node_a.chain().head().hash, b2.hash);
+    assert_eq!(node_a.chain().work(), node_b.chain().work());
+    assert_eq!(node_a.state_root(), node_b.state_root());
+    assert_eq!(inventory_len(&mut node_a, &player.id), 0);
+    assert_eq!(node_a.account_nonce(&miner_a.id), 0);
+    assert!(node_a.mempool().contains(&mint_hash));
+
+    // The orphaned mint goes into the next block on the winning branch.
+    let a3 = mine(&mut node_a, &miner_a);
+    assert!(a3.actions.iter().any(|signed| signed.hash() == mint_hash));
+    node_b.import_block(a3).unwrap();
+    assert_eq!(node_b.state_root(), node_a.state_root());
+}
+
+#[test]
+fn invalid_heavier_branch_is_dropped() {
+    let (miner_a, miner_b) = (Account::new(), Account::new());
+    let accounts = [&miner_a, &miner_b];
+    let (mut node_a, mut node_b) = (node(&accounts), node(&accounts));
+
+    let a1 = mine(&mut node_a, &miner_a);
+    let state_root = node_a.state_root();
+    let b1 = mine(&mut node_b, &miner_b);
+    let mut b2 = mine(&mut node_b, &miner_b);
+    b2.state_root = b1.state_root.clone();
+    assert!(b2.mine(MAX_NONCE_ATTEMPTS));
+
+    node_a.import_block(b1.clone()).unwrap();
+    let error = node_a.import_block(b2.clone()).unwrap_err();
+    assert!(error.contains("state root"), "unexpected error: {}", error);
+    assert_eq!(node_a.chain().head().hash, a1.hash);
+    assert_eq!(node_a.state_root(), state_root);
+    assert!(node_a.chain().get_block(&b1.hash).is_some());
+    assert!(node_a.chain().get_block(&b2.hash).is_none());
+}
+
+#[test]
+fn proof_of_authority_reorgs_return_every_orphaned_action() {
+    // Without stake, turns go round robin: `first` has even slots, `second` odd ones.
+    let (first, second, player) = (Account::new(), Account::new(), Account::new());
+    let node = || {
+        let validators = ValidatorSet::new(vec![first.id.clone(), second.id.clone()], SLOT_SECS);
+        let mut blockchain = Blockchain::with_consensus(Consensus::ProofOfAuthority(validators));
+        blockchain.allocate_mtoshi(&player.id, 100 * DTOSHI).unwrap();
+        blockchain
+    };
+    let slot_time = |slot: u64| GENESIS_TIMESTAMP + slot * SLOT_SECS + 1;
+    let (mut node_a, mut node_b) = (node(), node());
+
+    // Authority blocks have no producer action, so the player's comes first.
+    let hash = player.submit(&mut node_a, Action::RecordActivity { player: player.id.clone(), activity_type: 1 });
+    let a1 = node_a.propose_block(&second.keypair, slot_time(1)).unwrap();
+    assert_eq!(a1.actions[0].hash(), hash);
+
+    let b2 = node_b.propose_block(&first.keypair, slot_time(2)).unwrap();
+    let b3 = node_b.propose_block(&second.keypair, slot_time(3)).unwrap();
+    node_a.import_block(b2).unwrap();
+    node_a.import_block(b3.clone()).unwrap();
+    assert_eq!(node_a.chain().head().hash, b3.hash);
+    assert!(node_a.mempool().contains(&hash));
+
+    let a4 = node_a.propose_block(&first.keypair, slot_time(4)).unwrap();
+    assert!(a4.actions.iter().any(|signed| signed.hash() == hash));
+    node_b.import_block(a4).unwrap();
+    assert_eq!(node_b.state_root(), node_a.state_root());
+}
@@ -0,0 +1,41 @@
+use shared::network::load_or_create_keypair;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+    std::env::temp_dir().join(format!("catena-{}-{}-{}", name, std::process::id(), nanos))
+}
+
+#[test]
+fn identity_survives_restarts() {
+    let dir = temp_dir("identity");
+    let path = dir.join("node_keys.json");
+
+    let first = load_or_create_keypair(&path).unwrap();
+    let second = load_or_create_keypair(&path).unwrap();
+    assert_eq!(first.public().to_peer_id(), second.public().to_peer_id());
+
+    let key_file: serde_json::Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    assert_eq!(key_file["peer_id"], first.public().to_peer_id().to_string());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn reads_generate_keys_output_and_rejects_mismatches() {
+    let dir = temp_dir("keyfile");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("node_keys.json");
+    let private_key = "08011240b6220d54c2f3abae045b26321c1fe021f282affaa94f4d24147aec01896efe693efae0d0d36294377f9377822b7d871093fb2e9aad827d6e2653c90c0e6ec687";
+
+    let key_file = serde_json::json!({ "peer_id": "12D3KooWE4DKkEB1SDQj69U8kkPoMUxL9pGrmEmJfSWw7ZvVpAcW", "private_key": private_key });
+    std::fs::write(&path, serde_json::to_vec_pretty(&key_file).unwrap()).unwrap();
+    let keypair = load_or_create_keypair(&path).unwrap();
+    assert_eq!(keypair.public().to_peer_id().to_string(), "12D3KooWE4DKkEB1SDQj69U8kkPoMUxL9pGrmEmJfSWw7ZvVpAcW");
+
+    let other = libp2p::identity::Keypair::generate_ed25519().public().to_peer_id().to_string();
+    let key_file = serde_json::json!({ "peer_id": other, "private_key": private_key });
+    std::fs::write(&path, serde_json::to_vec_pretty(&key_file).unwrap()).unwrap();
+    assert!(load_or_create_keypair(&path).is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
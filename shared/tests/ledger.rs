@@ -0,0 +1,78 @@
+mod common;
+
+use common::Account;
+use shared::blockchain::{Action, ActionResult, Blockchain, FAUCET_AMOUNT};
+use shared::error::GameError;
+use shared::ledger::{MTOSHI, SOLUTIO};
+
+const DTOSHI: u64 = 1_000_000_000;
+
+fn balance(blockchain: &mut Blockchain, player: &str, token: &str) -> u64 {
+    match blockchain.process_action(Action::GetBalance { player: player.to_string(), token: token.to_string() }) {
+        ActionResult::Balance { balance, .. } => balance,
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn every_query_reads_the_same_balances() {
+    let mut blockchain = Blockchain::new();
+    let (player, miner) = (Account::new(), Account::new());
+    blockchain.allocate_mtoshi(&miner.id, 100 * DTOSHI).unwrap();
+
+    let granted = FAUCET_AMOUNT.units();
+    for token in [MTOSHI, SOLUTIO] {
+        let request = Action::RequestTokens { player: player.id.clone(), token: token.to_string() };
+        match player.execute(&mut blockchain, &miner, request) {
+            ActionResult::TokensRequested { amount, .. } => assert_eq!(amount, granted),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    let mtoshi = match blockchain.process_action(Action::GetMtoshiBalance { player: player.id.clone() }) {
+        ActionResult::MtoshiBalance { balance, .. } => balance,
+        other => panic!("unexpected result: {:?}", other),
+    };
+    assert_eq!(mtoshi, granted);
+    assert_eq!(balance(&mut blockchain, &player.id, MTOSHI), mtoshi);
+    assert_eq!(blockchain.ledger().balance(&player.id, MTOSHI), FAUCET_AMOUNT);
+
+    match blockchain.process_action(Action::GetPlayer { player: player.id.clone() }) {
+        ActionResult::Player { data, .. } => {
+            assert_eq!(data[MTOSHI], granted.to_string());
+            assert_eq!(data[SOLUTIO], granted.to_string());
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+    match blockchain.process_action(Action::ShowLeaderboard) {
+        ActionResult::Leaderboard { players } => assert!(players.contains(&(player.id.clone(), 1, 0, granted, granted))),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn unknown_tokens_are_refused() {
+    let mut blockchain = Blockchain::new();
+    let (player, miner) = (Account::new(), Account::new());
+    blockchain.allocate_mtoshi(&miner.id, 100 * DTOSHI).unwrap();
+
+    match blockchain.process_action(Action::GetTokens) {
+        ActionResult::Tokens { tokens } => {
+            let ids: Vec<&str> = tokens.iter().map(|(id, _)| id.as_str()).collect();
+            assert_eq!(ids, vec!["Solutio", "MTOSHI"]);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    let request = Action::RequestTokens { player: player.id.clone(), token: "GOLD".to_string() };
+    match player.execute(&mut blockchain, &miner, request) {
+        ActionResult::Error { error } => assert_eq!(error, GameError::not_found("Token", "GOLD")),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert!(blockchain.ledger().balances(&player.id).is_none());
+    // DTOSHI only names whole MTOSHI; nobody holds it as a token.
+    for token in ["GOLD", "DTOSHI"] {
+        let query = Action::GetBalance { player: player.id.clone(), token: token.to_string() };
+        assert!(matches!(blockchain.process_action(query), ActionResult::Error { error: GameError::NotFound { .. } }));
+    }
+}
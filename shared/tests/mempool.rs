@@ -0,0 +1,181 @@
+mod common;
+
+use common::Account;
+use shared::auth::SignedAction;
+use shared::blockchain::{Action, ActionResult, Blockchain, BASE_FEE_STANDARD};
+use shared::error::GameError;
+use shared::mempool::{AccountState, Mempool, MempoolError};
+
+const DTOSHI: u64 = 1_000_000_000;
+const NOW: u64 = 1_000;
+
+fn record(account: &Account, nonce: u64, tip: u64) -> SignedAction {
+    SignedAction::sign_with_tip(&account.keypair, Action::RecordActivity { player: account.id.clone(), activity_type: 1 }, nonce, tip)
+}
+
+fn funded(_: &str) -> AccountState {
+    AccountState { nonce: 0, balance: u64::MAX }
+}
+
+fn insert(mempool: &mut Mempool, signed: SignedAction) -> Result<String, MempoolError> {
+    mempool.insert(signed, funded, NOW)
+}
+
+#[test]
+fn only_transactions_are_pooled() {
+    let mut mempool = Mempool::default();
+    let player = Account::new();
+    for action in [
+        Action::GetNonce { player: player.id.clone() },
+        Action::DropTransaction { hash: "00".repeat(32) },
+        Action::MineBlock { miner: player.id.clone() },
+    ] {
+        let signed = SignedAction::sign(&player.keypair, action, 0);
+        assert_eq!(insert(&mut mempool, signed), Err(MempoolError::NotATransaction));
+    }
+
+    let mut forged = record(&player, 0, 0);
+    forged.action = Action::RecordActivity { player: player.id.clone(), activity_type: 2 };
+    assert!(matches!(insert(&mut mempool, forged), Err(MempoolError::Auth(_))));
+    assert!(mempool.is_empty());
+}
+
+#[test]
+fn stale_nonces_and_unpayable_fees_are_refused() {
+    let mut mempool = Mempool::default();
+    let player = Account::new();
+    let account = |_: &str| AccountState { nonce: 3, balance: 2 * BASE_FEE_STANDARD };
+
+    assert_eq!(mempool.insert(record(&player, 2, 0), account, NOW), Err(MempoolError::StaleNonce { expected: 3, got: 2 }));
+    mempool.insert(record(&player, 3, 0), account, NOW).unwrap();
+    mempool.insert(record(&player, 4, 0), account, NOW).unwrap();
+    // The balance has to cover everything the sender has queued.
+    assert_eq!(
+        mempool.insert(record(&player, 5, 0), account, NOW),
+        Err(MempoolError::InsufficientFee { needed: 3 * BASE_FEE_STANDARD, available: 2 * BASE_FEE_STANDARD })
+    );
+    assert_eq!(mempool.len(), 2);
+}
+
+#[test]
+fn a_higher_fee_replaces_a_pending_action() {
+    let mut mempool = Mempool::default();
+    let player = Account::new();
+    let original = insert(&mut mempool, record(&player, 0, 10)).unwrap();
+
+    assert_eq!(insert(&mut mempool, record(&player, 0, 10)), Err(MempoolError::Duplicate));
+    let same_fee = SignedAction::sign_with_tip(&player.keypair, Action::RecordActivity { player: player.id.clone(), activity_type: 2 }, 0, 10);
+    assert_eq!(insert(&mut mempool, same_fee), Err(MempoolError::Duplicate));
+    assert_eq!(insert(&mut mempool, record(&player, 0, 5)), Err(MempoolError::Duplicate));
+
+    let bumped = insert(&mut mempool, record(&player, 0, 11)).unwrap();
+    assert!(!mempool.contains(&original));
+    assert!(mempool.contains(&bumped));
+    assert_eq!(mempool.len(), 1);
+}
+
+#[test]
+fn each_sender_has_a_limit() {
+    let mut mempool = Mempool::new(10, 2, 600);
+    let (player, other) = (Account::new(), Account::new());
+    insert(&mut mempool, record(&player, 0, 0)).unwrap();
+    insert(&mut mempool, record(&player, 1, 0)).unwrap();
+    assert_eq!(insert(&mut mempool, record(&player, 2, 0)), Err(MempoolError::SenderLimit { sender: player.id.clone(), limit: 2 }));
+
+    // Replacing a queued action does not count against the limit.
+    insert(&mut mempool, record(&player, 1, 1)).unwrap();
+    insert(&mut mempool, record(&other, 0, 0)).unwrap();
+    assert_eq!(mempool.len(), 3);
+}
+
+#[test]
+fn a_full_pool_evicts_its_cheapest_tail() {
+    let mut mempool = Mempool::new(2, 10, 600);
+    let (first, second, third) = (Account::new(), Account::new(), Account::new());
+    let cheap = insert(&mut mempool, record(&first, 0, 0)).unwrap();
+    let dear = insert(&mut mempool, record(&second, 0, 5)).unwrap();
+
+    assert_eq!(insert(&mut mempool, record(&third, 0, 0)), Err(MempoolError::Full));
+    let middle = insert(&mut mempool, record(&third, 0, 1)).unwrap();
+    assert!(!mempool.contains(&cheap));
+    assert!(mempool.contains(&dear) && mempool.contains(&middle));
+    assert_eq!(mempool.len(), 2);
+}
+
+#[test]
+fn eviction_never_leaves_a_nonce_gap() {
+    let mut mempool = Mempool::new(2, 10, 600);
+    let (sender, newcomer) = (Account::new(), Account::new());
+    let head = insert(&mut mempool, record(&sender, 0, 0)).unwrap();
+    insert(&mut mempool, record(&sender, 1, 9)).unwrap();
+
+    // The sender's first action is the cheapest, but evicting it would strand
+    // the second, and the second pays more than the newcomer.
+    assert_eq!(insert(&mut mempool, record(&newcomer, 0, 1)), Err(MempoolError::Full));
+    assert!(mempool.contains(&head));
+}
+
+#[test]
+fn selection_follows_fees_within_nonce_order() {
+    let mut mempool = Mempool::default();
+    let (alice, bob) = (Account::new(), Account::new());
+    let alice_first = insert(&mut mempool, record(&alice, 0, 0)).unwrap();
+    let alice_second = insert(&mut mempool, record(&alice, 1, 100)).unwrap();
+    let bob_first = insert(&mut mempool, record(&bob, 0, 50)).unwrap();
+    let hashes = |selected: Vec<SignedAction>| selected.iter().map(SignedAction::hash).collect::<Vec<_>>();
+
+    // Alice's well-paid second action has to wait for her first.
+    let selected = hashes(mempool.select(|_| 0, 10));
+    assert_eq!(selected, vec![bob_first.clone(), alice_first.clone(), alice_second.clone()]);
+    assert_eq!(hashes(mempool.select(|_| 0, 2)), vec![bob_first.clone(), alice_first.clone()]);
+
+    // Once her first nonce is used, the second goes first.
+    let nonce = |sender: &str| if sender == alice.id { 1 } else { 0 };
+    assert_eq!(hashes(mempool.select(nonce, 10)), vec![alice_second.clone(), bob_first.clone()]);
+
+    // Nothing past a gap is selected.
+    let mut gapped = Mempool::default();
+    insert(&mut gapped, record(&alice, 1, 100)).unwrap();
+    assert!(gapped.select(|_| 0, 10).is_empty());
+
+    mempool.prune(nonce, NOW);
+    assert!(!mempool.contains(&alice_first));
+    assert_eq!(mempool.len(), 2);
+}
+
+#[test]
+fn old_entries_are_pruned() {
+    let mut mempool = Mempool::new(10, 10, 600);
+    let (early, late) = (Account::new(), Account::new());
+    let old = mempool.insert(record(&early, 0, 0), funded, NOW).unwrap();
+    let recent = mempool.insert(record(&late, 0, 0), funded, NOW + 300).unwrap();
+
+    mempool.prune(|_| 0, NOW + 600);
+    assert_eq!(mempool.len(), 2);
+    mempool.prune(|_| 0, NOW + 601);
+    assert!(!mempool.contains(&old));
+    assert!(mempool.contains(&recent));
+}
+
+#[test]
+fn only_the_operator_can_drop_pending_actions() {
+    let mut blockchain = Blockchain::new();
+    let (operator, player) = (Account::new(), Account::new());
+    blockchain.allocate_mtoshi(&player.id, 100 * DTOSHI).unwrap();
+    let hash = player.submit(&mut blockchain, Action::RecordActivity { player: player.id.clone(), activity_type: 1 });
+    let drop = Action::DropTransaction { hash: hash.clone() };
+
+    let refused = |result: ActionResult| matches!(result, ActionResult::Error { error: GameError::Unauthorized { .. } });
+    assert!(refused(blockchain.process_action(drop.clone())));
+    assert!(refused(blockchain.process_signed_action(player.sign(&blockchain, drop.clone())).unwrap()));
+    assert!(refused(blockchain.process_signed_action(operator.sign(&blockchain, drop.clone())).unwrap()), "no operator is set");
+    assert!(blockchain.mempool().contains(&hash));
+
+    blockchain.set_operator(operator.id.clone());
+    assert!(refused(blockchain.process_signed_action(player.sign(&blockchain, drop.clone())).unwrap()));
+    match blockchain.process_signed_action(operator.sign(&blockchain, drop)).unwrap() {
+        ActionResult::TransactionDropped { dropped, .. } => assert!(dropped),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert!(!blockchain.mempool().contains(&hash));
+}
@@ -0,0 +1,54 @@
+use shared::merkle::{build_proof, empty_root, merkle_root, verify_proof};
+use shared::runtime::catena_node::{Block, Transaction};
+
+fn leaves(count: usize) -> Vec<Vec<u8>> {
+    (0..count).map(|i| format!("leaf {}", i).into_bytes()).collect()
+}
+
+#[test]
+fn every_leaf_has_an_inclusion_proof() {
+    for count in 1..=9 {
+        let leaves = leaves(count);
+        let root = merkle_root(&leaves);
+        for (index, leaf) in leaves.iter().enumerate() {
+            let proof = build_proof(&leaves, index).unwrap();
+            assert_eq!(proof.leaf_index, index);
+            assert!(verify_proof(&root, leaf, &proof), "leaf {} of {} does not verify", index, count);
+        }
+        assert!(build_proof(&leaves, count).is_none());
+    }
+    assert_eq!(merkle_root(&[]), empty_root());
+}
+
+#[test]
+fn proofs_do_not_verify_other_data() {
+    let leaves = leaves(5);
+    let root = merkle_root(&leaves);
+    let proof = build_proof(&leaves, 2).unwrap();
+
+    assert!(!verify_proof(&root, b"leaf 7", &proof));
+    assert!(!verify_proof(&root, &leaves[3], &proof));
+    assert!(!verify_proof(&merkle_root(&leaves[..4]), &leaves[2], &proof));
+
+    let mut tampered = proof.clone();
+    tampered.steps[0].hash = "00".repeat(32);
+    assert!(!verify_proof(&root, &leaves[2], &tampered));
+    let mut truncated = proof;
+    truncated.steps.pop();
+    assert!(!verify_proof(&root, &leaves[2], &truncated));
+}
+
+#[test]
+fn transactions_are_proven_against_the_block_root() {
+    let transactions: Vec<Transaction> = (0..3u128)
+        .map(|amount| Transaction { sender: "alice".to_string(), receiver: "bob".to_string(), amount, token: "MTOSHI".to_string() })
+        .collect();
+    let block = Block::new(1, 0, String::new(), transactions, vec![], String::new(), 1);
+    for (index, transaction) in block.transactions.iter().enumerate() {
+        let proof = block.transaction_proof(index).unwrap();
+        assert!(Block::verify_transaction(&block.tx_root, transaction, &proof));
+    }
+    let forged = Transaction { amount: 1_000, ..block.transactions[1].clone() };
+    assert!(!Block::verify_transaction(&block.tx_root, &forged, &block.transaction_proof(1).unwrap()));
+    assert!(block.transaction_proof(3).is_none());
+}
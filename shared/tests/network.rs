@@ -0,0 +1,146 @@
+use libp2p::swarm::{ConnectionId, SwarmEvent};
+use libp2p::{Multiaddr, PeerId};
+use shared::network::{setup_network, BootstrapPeers, ConnectionState, Network, NetworkConfig, MAX_REDIAL_DELAY};
+use std::time::{Duration, Instant};
+
+fn config(dial_addrs: Vec<String>) -> NetworkConfig {
+    NetworkConfig { listen_addr: "/ip4/127.0.0.1/tcp/0".to_string(), dial_addrs, key_file: None, mdns: false }
+}
+
+async fn listen_addr(network: &mut Network) -> Multiaddr {
+    loop {
+        if let SwarmEvent::NewListenAddr { address, .. } = network.next_event().await {
+            return address;
+        }
+    }
+}
+
+/// Drives both networks until `done` holds for the dialer, failing after ten seconds.
+async fn drive_until(dialer: &mut Network, other: Option<&mut Network>, done: impl Fn(&Network) -> bool) {
+    let deadline = tokio::time::sleep(Duration::from_secs(10));
+    tokio::pin!(deadline);
+    let mut other = other;
+    while !done(dialer) {
+        tokio::select! {
+            _ = dialer.next_event() => {}
+            _ = async {
+                match other.as_mut() {
+                    Some(other) => { other.next_event().await; }
+                    None => std::future::pending::<()>().await,
+                }
+            } => {}
+            _ = &mut deadline => panic!("timed out; bootstrap peers: {:?}", dialer.bootstrap_peers()),
+        }
+    }
+}
+
+#[test]
+fn redials_back_off_exponentially() {
+    let now = Instant::now();
+    let addr: Multiaddr = "/ip4/127.0.0.1/tcp/4001".parse().unwrap();
+    let mut peers = BootstrapPeers::new(vec![addr], now);
+    assert_eq!(peers.due(now), vec![0]);
+    assert_eq!(peers.next_retry(), Some(now));
+
+    let mut delays = vec![];
+    for attempt in 0..10 {
+        let dial = ConnectionId::new_unchecked(attempt);
+        peers.dialing(0, dial);
+        assert_eq!(peers.peers()[0].state, ConnectionState::Dialing);
+        assert!(peers.due(now).is_empty());
+        peers.dial_failed(dial, now);
+        match peers.peers()[0].state {
+            ConnectionState::Backoff { retry_at } => delays.push(retry_at - now),
+            state => panic!("unexpected state {:?}", state),
+        }
+    }
+    let secs: Vec<u64> = delays.iter().map(Duration::as_secs).collect();
+    assert_eq!(secs, vec![1, 2, 4, 8, 16, 32, 60, 60, 60, 60]);
+    assert_eq!(*delays.last().unwrap(), MAX_REDIAL_DELAY);
+
+    let peer_id = PeerId::random();
+    let dial = ConnectionId::new_unchecked(100);
+    peers.dialing(0, dial);
+    peers.connected(dial, peer_id);
+    assert_eq!(peers.peers()[0].state, ConnectionState::Connected);
+    assert_eq!(peers.peers()[0].failures, 0);
+    assert_eq!(peers.next_retry(), None);
+
+    peers.disconnected(peer_id, now);
+    assert_eq!(peers.peers()[0].state, ConnectionState::Backoff { retry_at: now + Duration::from_secs(1) });
+}
+
+#[tokio::test]
+async fn dials_bootstrap_peers_and_redials_after_a_drop() {
+    let mut bootstrap = setup_network(config(vec![])).await.unwrap();
+    let bootstrap_id = *bootstrap.swarm.local_peer_id();
+    let addr = listen_addr(&mut bootstrap).await.with(libp2p::multiaddr::Protocol::P2p(bootstrap_id));
+
+    let mut node = setup_network(config(vec![addr.to_string()])).await.unwrap();
+    assert_eq!(node.bootstrap_peers()[0].peer_id, Some(bootstrap_id));
+    drive_until(&mut node, Some(&mut bootstrap), |node| node.bootstrap_peers()[0].state == ConnectionState::Connected).await;
+    assert!(node.is_connected(&bootstrap_id));
+
+    drop(bootstrap);
+    drive_until(&mut node, None, |node| node.bootstrap_peers()[0].failures >= 2).await;
+    assert!(node.connected_peers().is_empty());
+    assert!(matches!(node.bootstrap_peers()[0].state, ConnectionState::Backoff { .. } | ConnectionState::Dialing));
+}
+
+#[tokio::test]
+async fn nodes_discover_each_other_from_one_bootstrap_address() {
+    let mut first = setup_network(config(vec![])).await.unwrap();
+    let first_id = *first.swarm.local_peer_id();
+    let addr = listen_addr(&mut first).await.with(libp2p::multiaddr::Protocol::P2p(first_id));
+    let mut nodes = vec![first];
+    for _ in 0..4 {
+        nodes.push(setup_network(config(vec![addr.to_string()])).await.unwrap());
+    }
+    let ids: Vec<PeerId> = nodes.iter().map(|node| *node.swarm.local_peer_id()).collect();
+
+    let knows_everyone = |nodes: &mut Vec<Network>| {
+        nodes.iter_mut().zip(&ids).all(|(node, id)| {
+            let known: Vec<String> = node.get_peers().into_iter().filter(|peer| peer.connected).map(|peer| peer.peer_id).collect();
+            ids.iter().filter(|other| *other != id).all(|other| known.contains(&other.to_string()))
+        })
+    };
+    let discovery = async {
+        while !knows_everyone(&mut nodes) {
+            let events = nodes.iter_mut().map(|node| Box::pin(node.next_event()));
+            libp2p::futures::future::select_all(events).await;
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(20), discovery).await.expect("every node finds the other four");
+
+    let peers = nodes[4].get_peers();
+    assert_eq!(peers.len(), 4);
+    assert!(peers.iter().all(|peer| !peer.addresses.is_empty()));
+}
+
+#[tokio::test]
+async fn mdns_is_off_unless_configured() {
+    let config: NetworkConfig = serde_json::from_str(r#"{ "listen_addr": "/ip4/127.0.0.1/tcp/0", "dial_addrs": [] }"#).unwrap();
+    assert!(!config.mdns);
+    let network = setup_network(config).await.unwrap();
+    assert!(!network.swarm.behaviour().mdns_enabled());
+}
+
+// Needs multicast, which sandboxes and many CI runners lack. Run it with
+// `cargo test -- --ignored` on a LAN.
+#[tokio::test]
+#[ignore = "needs multicast on a non-loopback interface"]
+async fn mdns_nodes_find_each_other_without_dial_lists() {
+    // mDNS announces the LAN address, so the nodes listen on every interface.
+    let mdns_config = || NetworkConfig { listen_addr: "/ip4/0.0.0.0/tcp/0".to_string(), mdns: true, ..config(vec![]) };
+    let mut nodes = [setup_network(mdns_config()).await.unwrap(), setup_network(mdns_config()).await.unwrap()];
+    assert!(nodes[0].swarm.behaviour().mdns_enabled());
+    let other = *nodes[1].swarm.local_peer_id();
+
+    let discovery = async {
+        while !nodes[0].is_connected(&other) {
+            let events = nodes.iter_mut().map(|node| Box::pin(node.next_event()));
+            libp2p::futures::future::select_all(events).await;
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(20), discovery).await.expect("nodes find each other over mDNS");
+}
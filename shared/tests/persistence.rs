@@ -0,0 +1,147 @@
+mod common;
+
+use common::{mine, Account};
+use libp2p::identity::Keypair;
+use shared::auth::{account_id, SignedAction};
+use shared::blockchain::{Action, ActionResult, Blockchain};
+use shared::storage::Storage;
+use std::io;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DTOSHI: u64 = 1_000_000_000;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+    std::env::temp_dir().join(format!("catena-{}-{}-{}", name, std::process::id(), nanos))
+}
+
+fn mtoshi_balance(blockchain: &mut Blockchain, player: &str) -> u64 {
+    match blockchain.process_action(Action::GetMtoshiBalance { player: player.to_string() }) {
+        ActionResult::MtoshiBalance { balance, .. } => balance,
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn balances_survive_restart() {
+    let dir = temp_dir("restart");
+    let miner = Keypair::generate_ed25519();
+    let miner_id = account_id(&miner.public());
+    let player = Keypair::generate_ed25519();
+    let player_id = account_id(&player.public());
+
+    let (miner_balance, player_balance, head_hash) = {
+        let mut blockchain = Blockchain::open(&dir).unwrap();
+        blockchain.allocate_mtoshi(&miner_id, 10 * DTOSHI).unwrap();
+        blockchain.allocate_mtoshi(&player_id, 20 * DTOSHI).unwrap();
+        let post = SignedAction::sign(&player, Action::PostContent {
+            player: player_id.clone(),
+            post_id: "post1".to_string(),
+            caption: "persisted".to_string(),
+        }, 0);
+        blockchain.submit_action(post).unwrap();
+        let mine = SignedAction::sign(&miner, Action::MineBlock { miner: miner_id.clone() }, 0);
+        match blockchain.process_signed_action(mine).unwrap() {
+            ActionResult::BlockMined { block: Some(_), .. } => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        (
+            mtoshi_balance(&mut blockchain, &miner_id),
+            mtoshi_balance(&mut blockchain, &player_id),
+            blockchain.chain().head().hash.clone(),
+        )
+    };
+
+    let mut restored = Blockchain::open(&dir).unwrap();
+    assert_eq!(restored.chain().height(), 1);
+    assert_eq!(restored.chain().head().hash, head_hash);
+    assert_eq!(restored.account_nonce(&player_id), 1);
+    assert_eq!(mtoshi_balance(&mut restored, &miner_id), miner_balance);
+    assert_eq!(mtoshi_balance(&mut restored, &player_id), player_balance);
+    assert!(player_balance < 20 * DTOSHI);
+    match restored.process_action(Action::GetPosts) {
+        ActionResult::Posts { posts } => assert_eq!(posts.len(), 1),
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn the_finalized_height_survives_restart() {
+    let dir = temp_dir("finalized");
+    let miner = Keypair::generate_ed25519();
+    let miner_id = account_id(&miner.public());
+
+    let finalized = {
+        let mut blockchain = Blockchain::open(&dir).unwrap();
+        blockchain.allocate_mtoshi(&miner_id, 10 * DTOSHI).unwrap();
+        let mut hashes = vec![];
+        for nonce in 0..2 {
+            let mine = SignedAction::sign(&miner, Action::MineBlock { miner: miner_id.clone() }, nonce);
+            match blockchain.process_signed_action(mine).unwrap() {
+                ActionResult::BlockMined { block: Some(block), .. } => hashes.push(block.hash),
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+        // Finality arrives after the last block was stored.
+        blockchain.finalize(&hashes[0]).unwrap();
+        hashes.swap_remove(0)
+    };
+
+    let restored = Blockchain::open(&dir).unwrap();
+    assert_eq!(restored.chain().height(), 2);
+    assert_eq!(restored.chain().finalized_head().hash, finalized);
+    assert_eq!(restored.chain().finalized_head().index, 1);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn a_snapshot_that_does_not_match_its_block_is_refused() {
+    let dir = temp_dir("mismatch");
+    let miner = Account::new();
+    {
+        let mut blockchain = Blockchain::open(&dir).unwrap();
+        blockchain.allocate_mtoshi(&miner.id, 10 * DTOSHI).unwrap();
+        mine(&mut blockchain, &miner);
+    }
+
+    // Overwrite the snapshot with state the block at its height never committed to.
+    Storage::open(&dir).unwrap().save_snapshot(1, 0, &Blockchain::new()).unwrap();
+    let error = Blockchain::open(&dir).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    assert!(error.to_string().contains("does not match the state root"), "unexpected error: {}", error);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn reorgs_below_the_reopened_head_are_refused() {
+    let dir = temp_dir("undo");
+    let (miner_a, miner_b) = (Account::new(), Account::new());
+    let fund = |blockchain: &mut Blockchain| {
+        for account in [&miner_a, &miner_b] {
+            blockchain.allocate_mtoshi(&account.id, 10 * DTOSHI).unwrap();
+        }
+    };
+    let a1 = {
+        let mut blockchain = Blockchain::open(&dir).unwrap();
+        fund(&mut blockchain);
+        mine(&mut blockchain, &miner_a)
+    };
+    let mut other = Blockchain::new();
+    fund(&mut other);
+    let b1 = mine(&mut other, &miner_b);
+    let b2 = mine(&mut other, &miner_b);
+
+    // Undo checkpoints are not persisted, so the state before a1 is gone.
+    let mut restored = Blockchain::open(&dir).unwrap();
+    restored.import_block(b1).unwrap();
+    let error = restored.import_block(b2).unwrap_err();
+    assert!(error.contains("no longer available"), "unexpected error: {}", error);
+    assert_eq!(restored.chain().head().hash, a1.hash);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
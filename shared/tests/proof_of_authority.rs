@@ -0,0 +1,77 @@
+use libp2p::identity::Keypair;
+use shared::auth::{account_id, SignedAction};
+use shared::blockchain::{Action, ActionResult, Blockchain};
+use shared::chain::GENESIS_TIMESTAMP;
+use shared::consensus::{Consensus, ValidatorSet};
+
+const SLOT_SECS: u64 = 5;
+
+fn validators(count: usize) -> (Vec<Keypair>, Consensus) {
+    let keys: Vec<Keypair> = (0..count).map(|_| Keypair::generate_ed25519()).collect();
+    let ids = keys.iter().map(|key| account_id(&key.public())).collect();
+    (keys, Consensus::ProofOfAuthority(ValidatorSet::new(ids, SLOT_SECS)))
+}
+
+fn slot_time(slot: u64) -> u64 {
+    GENESIS_TIMESTAMP + slot * SLOT_SECS + 1
+}
+
+#[test]
+fn validators_take_turns_and_missed_slots_are_skipped() {
+    let (keys, consensus) = validators(5);
+    let mut producer = Blockchain::with_consensus(consensus.clone());
+    let mut follower = Blockchain::with_consensus(consensus);
+
+    // Slot 1 belongs to the second validator only.
+    assert!(producer.propose_block(&keys[0], slot_time(1)).is_none());
+    let first = producer.propose_block(&keys[1], slot_time(1)).unwrap();
+    assert_eq!(first.slot, 1);
+    assert!(producer.propose_block(&keys[1], slot_time(1)).is_none());
+
+    // The validators of slots 2 and 3 are offline; slot 4 builds on slot 1.
+    let second = producer.propose_block(&keys[4], slot_time(4)).unwrap();
+    assert_eq!(second.index, 2);
+    assert_eq!(second.previous_hash, first.hash);
+
+    follower.import_block(first).unwrap();
+    follower.import_block(second).unwrap();
+    assert_eq!(follower.chain().head().slot, 4);
+    assert_eq!(follower.state_root(), producer.state_root());
+}
+
+#[test]
+fn blocks_from_the_wrong_signer_or_slot_are_rejected() {
+    let (keys, consensus) = validators(5);
+    let mut producer = Blockchain::with_consensus(consensus.clone());
+    let mut follower = Blockchain::with_consensus(consensus);
+    let block = producer.propose_block(&keys[2], slot_time(2)).unwrap();
+
+    let mut wrong_signer = block.clone();
+    wrong_signer.seal(&keys[3], 2);
+    let error = follower.import_block(wrong_signer).unwrap_err();
+    assert!(error.contains("belongs to"), "unexpected error: {}", error);
+
+    let mut wrong_slot = block.clone();
+    wrong_slot.seal(&keys[3], 3);
+    let error = follower.import_block(wrong_slot).unwrap_err();
+    assert!(error.contains("outside slot"), "unexpected error: {}", error);
+
+    let mut forged = block.clone();
+    let flipped = if forged.signature.ends_with('0') { "1" } else { "0" };
+    let last = forged.signature.len() - 1;
+    forged.signature.replace_range(last.., flipped);
+    assert!(follower.import_block(forged).is_err());
+
+    follower.import_block(block).unwrap();
+    assert_eq!(follower.chain().height(), 1);
+}
+
+#[test]
+fn mine_block_is_refused_under_proof_of_authority() {
+    let (keys, consensus) = validators(1);
+    let mut blockchain = Blockchain::with_consensus(consensus);
+    let miner = account_id(&keys[0].public());
+    let mine = SignedAction::sign(&keys[0], Action::MineBlock { miner }, 0);
+    assert!(matches!(blockchain.process_signed_action(mine), Ok(ActionResult::Error { .. })));
+    assert_eq!(blockchain.chain().height(), 0);
+}
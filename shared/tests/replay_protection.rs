@@ -0,0 +1,131 @@
+mod common;
+
+use common::{mine, receipt, Account};
+use shared::auth::SignedAction;
+use shared::blockchain::{Action, ActionResult, Blockchain};
+use shared::mempool::MempoolError;
+
+const DTOSHI: u64 = 1_000_000_000;
+
+fn mtoshi_balance(blockchain: &mut Blockchain, player: &str) -> u64 {
+    match blockchain.process_action(Action::GetMtoshiBalance { player: player.to_string() }) {
+        ActionResult::MtoshiBalance { balance, .. } => balance,
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+/// Queues `signed`, mines it into a block and checks that it succeeded.
+fn execute(blockchain: &mut Blockchain, miner: &Account, signed: SignedAction) -> ActionResult {
+    let hash = blockchain.submit_action(signed).expect("action accepted");
+    mine(blockchain, miner);
+    let result = receipt(blockchain, &hash);
+    assert!(!matches!(result, ActionResult::Error { .. }), "action failed: {:?}", result);
+    result
+}
+
+#[test]
+fn replayed_paid_like_has_no_effect() {
+    let mut blockchain = Blockchain::new();
+    let (creator, fan, miner) = (Account::new(), Account::new(), Account::new());
+    for account in [&creator, &fan, &miner] {
+        blockchain.allocate_mtoshi(&account.id, 100 * DTOSHI).unwrap();
+    }
+
+    let post = creator.sign(&blockchain, Action::PostContent {
+        player: creator.id.clone(),
+        post_id: "post1".to_string(),
+        caption: "hello".to_string(),
+    });
+    execute(&mut blockchain, &miner, post);
+
+    let like = fan.sign(&blockchain, Action::LikePost {
+        player: fan.id.clone(),
+        post_id: "post1".to_string(),
+        paid: true,
+    });
+    execute(&mut blockchain, &miner, like.clone());
+    let fan_balance = mtoshi_balance(&mut blockchain, &fan.id);
+    let creator_balance = mtoshi_balance(&mut blockchain, &creator.id);
+
+    let replay = blockchain.process_signed_action(like);
+    assert_eq!(replay.unwrap_err(), MempoolError::StaleNonce { expected: 1, got: 0 });
+    mine(&mut blockchain, &miner);
+    assert_eq!(mtoshi_balance(&mut blockchain, &fan.id), fan_balance);
+    assert_eq!(mtoshi_balance(&mut blockchain, &creator.id), creator_balance);
+    match blockchain.process_action(Action::GetPosts) {
+        ActionResult::Posts { posts } => assert_eq!(posts[0].3, 1),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn replayed_nft_purchase_has_no_effect() {
+    let mut blockchain = Blockchain::new();
+    let (seller, buyer, miner) = (Account::new(), Account::new(), Account::new());
+    for account in [&seller, &buyer, &miner] {
+        blockchain.allocate_mtoshi(&account.id, 100 * DTOSHI).unwrap();
+    }
+
+    let list_nft = |blockchain: &mut Blockchain| {
+        let mint = seller.sign(blockchain, Action::MintNft {
+            player: seller.id.clone(),
+            name: "Gem".to_string(),
+            value: 100,
+        });
+        execute(blockchain, &miner, mint);
+        let sell = seller.sign(blockchain, Action::SellNft {
+            seller: seller.id.clone(),
+            nft_index: 0,
+            price: 5 * DTOSHI,
+        });
+        execute(blockchain, &miner, sell);
+    };
+
+    list_nft(&mut blockchain);
+    let buy = buyer.sign(&blockchain, Action::BuyNft {
+        buyer: buyer.id.clone(),
+        seller: seller.id.clone(),
+        nft_index: 0,
+    });
+    execute(&mut blockchain, &miner, buy.clone());
+    let buyer_balance = mtoshi_balance(&mut blockchain, &buyer.id);
+
+    // A fresh listing at the same index would be bought again by a replay
+    // if nonces were not enforced.
+    list_nft(&mut blockchain);
+    let replay = blockchain.process_signed_action(buy);
+    assert!(matches!(replay, Err(MempoolError::StaleNonce { .. })));
+    mine(&mut blockchain, &miner);
+    assert_eq!(mtoshi_balance(&mut blockchain, &buyer.id), buyer_balance);
+    match blockchain.process_action(Action::GetNftListings) {
+        ActionResult::NftListings { listings } => assert_eq!(listings.len(), 1),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn stale_nonces_are_rejected_and_future_ones_wait_their_turn() {
+    let mut blockchain = Blockchain::new();
+    let (player, miner) = (Account::new(), Account::new());
+    blockchain.allocate_mtoshi(&player.id, 100 * DTOSHI).unwrap();
+    blockchain.allocate_mtoshi(&miner.id, 100 * DTOSHI).unwrap();
+    let record = |nonce| SignedAction::sign(&player.keypair, Action::RecordActivity {
+        player: player.id.clone(),
+        activity_type: 4,
+    }, nonce);
+
+    // A nonce from the future is held back until the gap before it is filled.
+    let second = blockchain.submit_action(record(1)).unwrap();
+    assert!(mine(&mut blockchain, &miner).actions.iter().all(|signed| signed.hash() != second));
+    assert!(blockchain.mempool().contains(&second));
+    let first = blockchain.submit_action(record(0)).unwrap();
+    let block = mine(&mut blockchain, &miner);
+    let included: Vec<String> = block.actions.iter().skip(1).map(SignedAction::hash).collect();
+    assert_eq!(included, vec![first, second]);
+
+    assert_eq!(blockchain.process_signed_action(record(1)).unwrap_err(), MempoolError::StaleNonce { expected: 2, got: 1 });
+    match blockchain.process_action(Action::GetNonce { player: player.id.clone() }) {
+        ActionResult::Nonce { nonce, .. } => assert_eq!(nonce, 2),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
@@ -0,0 +1,92 @@
+mod common;
+
+use common::{mine, receipt, Account};
+use shared::auth::{AuthError, SignedAction};
+use shared::blockchain::{Action, ActionResult, Blockchain};
+use shared::mempool::MempoolError;
+
+const DTOSHI: u64 = 1_000_000_000;
+
+fn battle(player1: &Account, player2: &Account) -> Action {
+    Action::Battle { player1: player1.id.clone(), player2: player2.id.clone() }
+}
+
+#[test]
+fn unsigned_and_wrongly_signed_actions_are_refused() {
+    let (player, other) = (Account::new(), Account::new());
+    let action = Action::RecordActivity { player: player.id.clone(), activity_type: 1 };
+    let signed = SignedAction::sign(&player.keypair, action.clone(), 0);
+    assert_eq!(signed.verify(), Ok(player.id.clone()));
+
+    let mut unsigned = signed.clone();
+    unsigned.signature.clear();
+    assert_eq!(unsigned.verify(), Err(AuthError::Unsigned));
+    let mut no_key = signed.clone();
+    no_key.public_key.clear();
+    assert_eq!(no_key.verify(), Err(AuthError::Unsigned));
+    let mut bad_key = signed.clone();
+    bad_key.public_key = "zz".to_string();
+    assert_eq!(bad_key.verify(), Err(AuthError::InvalidPublicKey));
+
+    let mut tampered = signed.clone();
+    tampered.action = Action::RecordActivity { player: player.id.clone(), activity_type: 4 };
+    assert_eq!(tampered.verify(), Err(AuthError::InvalidSignature));
+    let mut bumped = signed.clone();
+    bumped.nonce = 1;
+    assert_eq!(bumped.verify(), Err(AuthError::InvalidSignature));
+    let mut borrowed = signed;
+    borrowed.signature = SignedAction::sign(&other.keypair, action, 0).signature;
+    assert_eq!(borrowed.verify(), Err(AuthError::InvalidSignature));
+}
+
+#[test]
+fn signers_cannot_act_for_other_players() {
+    let (player, impostor) = (Account::new(), Account::new());
+    let signed = SignedAction::sign(&impostor.keypair, Action::Stake { player: player.id.clone(), amount: DTOSHI }, 0);
+    assert_eq!(signed.verify(), Err(AuthError::SignerMismatch { signer: impostor.id.clone(), player: player.id.clone() }));
+
+    let mut blockchain = Blockchain::new();
+    blockchain.allocate_mtoshi(&impostor.id, DTOSHI).unwrap();
+    assert!(matches!(blockchain.submit_action(signed), Err(MempoolError::Auth(AuthError::SignerMismatch { .. }))));
+    assert!(blockchain.mempool().is_empty());
+}
+
+#[test]
+fn battles_and_trades_need_both_players() {
+    let (alice, bob, carol) = (Account::new(), Account::new(), Account::new());
+    let trade = Action::TradeItems { player1: alice.id.clone(), player1_item_index: 0, player2: bob.id.clone(), player2_item_index: 0 };
+    for action in [battle(&alice, &bob), trade] {
+        let offer = SignedAction::sign(&alice.keypair, action, 0);
+        assert_eq!(offer.verify(), Err(AuthError::MissingCosignature { player: bob.id.clone() }));
+        assert_eq!(offer.clone().cosign(&bob.keypair).verify(), Ok(alice.id.clone()));
+        assert_eq!(
+            offer.clone().cosign(&carol.keypair).verify(),
+            Err(AuthError::SignerMismatch { signer: carol.id.clone(), player: bob.id.clone() })
+        );
+
+        // A cosignature only covers the offer it was made for.
+        let mut retargeted = offer.cosign(&bob.keypair);
+        retargeted.nonce = 1;
+        assert_eq!(retargeted.verify(), Err(AuthError::InvalidSignature));
+    }
+
+    let mut stray = SignedAction::sign(&alice.keypair, Action::RecordActivity { player: alice.id.clone(), activity_type: 1 }, 0);
+    stray.cosignature = SignedAction::sign(&bob.keypair, battle(&alice, &bob), 0).cosign(&bob.keypair).cosignature;
+    assert_eq!(stray.verify(), Err(AuthError::InvalidSignature));
+}
+
+#[test]
+fn a_cosigned_battle_is_included_in_a_block() {
+    let mut blockchain = Blockchain::new();
+    let (alice, bob, miner) = (Account::new(), Account::new(), Account::new());
+    for account in [&alice, &bob, &miner] {
+        blockchain.allocate_mtoshi(&account.id, DTOSHI).unwrap();
+    }
+
+    let offer = alice.sign(&blockchain, battle(&alice, &bob));
+    assert!(matches!(blockchain.submit_action(offer.clone()), Err(MempoolError::Auth(AuthError::MissingCosignature { .. }))));
+    let accepted = offer.cosign(&bob.keypair);
+    let hash = blockchain.submit_action(accepted).unwrap();
+    mine(&mut blockchain, &miner);
+    assert!(matches!(receipt(&mut blockchain, &hash), ActionResult::BattleResult { .. }));
+}
@@ -0,0 +1,164 @@
+mod common;
+
+use common::{receipt, Account};
+use libp2p::identity::Keypair;
+use shared::blockchain::{Action, ActionResult, Blockchain, BASE_FEE_STANDARD};
+use shared::chain::GENESIS_TIMESTAMP;
+use shared::consensus::{Consensus, ValidatorSet};
+use shared::error::GameError;
+use shared::runtime::catena_node::Block;
+use shared::staking::{DOUBLE_SIGN_SLASH_PERCENT, JAIL_MISSED_SLOTS, JAIL_PERIOD_BLOCKS};
+
+const DTOSHI: u64 = 1_000_000_000;
+const SLOT_SECS: u64 = 5;
+
+fn slot_time(slot: u64) -> u64 {
+    GENESIS_TIMESTAMP + slot * SLOT_SECS + 1
+}
+
+fn testnet(validators: &[&Account]) -> Blockchain {
+    let ids = validators.iter().map(|validator| validator.id.clone()).collect();
+    Blockchain::with_consensus(Consensus::ProofOfAuthority(ValidatorSet::new(ids, SLOT_SECS)))
+}
+
+/// Queues `action` from `account` and has `validator` include it in the
+/// block for `slot`.
+fn execute(blockchain: &mut Blockchain, account: &Account, action: Action, validator: &Account, slot: u64) -> ActionResult {
+    let hash = account.submit(blockchain, action);
+    blockchain.propose_block(&validator.keypair, slot_time(slot)).expect("validator's turn");
+    receipt(blockchain, &hash)
+}
+
+fn stake_of(blockchain: &mut Blockchain, player: &str) -> u64 {
+    match blockchain.process_action(Action::GetStake { player: player.to_string() }) {
+        ActionResult::StakeInfo { stake, .. } => stake,
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+fn community_pool(blockchain: &mut Blockchain) -> u64 {
+    match blockchain.process_action(Action::GetPools) {
+        ActionResult::Pools { community_pool, .. } => community_pool,
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+/// A second block signed by `keypair` at the same height and slot as `block`.
+fn double_sign(block: &Block, keypair: &Keypair) -> Block {
+    let mut forged = block.clone();
+    forged.timestamp += 1;
+    forged.seal(keypair, block.slot);
+    forged
+}
+
+/// A validator staking 50 DTOSHI on a single-validator chain with a funded
+/// reporter. The stake goes into the block for slot 1.
+fn staked_validator() -> (Blockchain, Account, Account) {
+    let (validator, reporter) = (Account::new(), Account::new());
+    let mut blockchain = testnet(&[&validator]);
+    blockchain.allocate_mtoshi(&validator.id, 100 * DTOSHI).unwrap();
+    blockchain.allocate_mtoshi(&reporter.id, 100 * DTOSHI).unwrap();
+    let stake = Action::Stake { player: validator.id.clone(), amount: 50 * DTOSHI };
+    assert!(matches!(execute(&mut blockchain, &validator, stake, &validator, 1), ActionResult::Staked { .. }));
+    (blockchain, validator, reporter)
+}
+
+#[test]
+fn double_signing_slashes_stake_into_the_community_pool() {
+    let (mut blockchain, validator, reporter) = staked_validator();
+    let block = blockchain.propose_block(&validator.keypair, slot_time(2)).unwrap();
+    let forged = double_sign(&block, &validator.keypair);
+
+    let pool_before = community_pool(&mut blockchain);
+    let evidence = Action::ReportDoubleSign { first: Box::new(block.without_body()), second: Box::new(forged.without_body()) };
+    let slashed = 50 * DTOSHI * DOUBLE_SIGN_SLASH_PERCENT / 100;
+    match execute(&mut blockchain, &reporter, evidence.clone(), &validator, 3) {
+        ActionResult::ValidatorSlashed { validator: slashed_validator, height, amount } => {
+            assert_eq!(slashed_validator, validator.id);
+            assert_eq!(height, 2);
+            assert_eq!(amount, slashed);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert_eq!(stake_of(&mut blockchain, &validator.id), 50 * DTOSHI - slashed);
+    assert_eq!(community_pool(&mut blockchain), pool_before + slashed + BASE_FEE_STANDARD / 10);
+
+    // The same offence is only punished once.
+    assert!(matches!(execute(&mut blockchain, &reporter, evidence, &validator, 4), ActionResult::Error { .. }));
+    assert_eq!(stake_of(&mut blockchain, &validator.id), 50 * DTOSHI - slashed);
+}
+
+#[test]
+fn invalid_evidence_is_rejected() {
+    let (mut blockchain, validator, reporter) = staked_validator();
+    let block = blockchain.propose_block(&validator.keypair, slot_time(2)).unwrap();
+    let next = blockchain.propose_block(&validator.keypair, slot_time(3)).unwrap();
+
+    let mut tampered = double_sign(&block, &validator.keypair);
+    tampered.timestamp += 1;
+    let mut bad_signature = double_sign(&block, &validator.keypair);
+    bad_signature.signature = block.signature.clone();
+    let other_signer = double_sign(&block, &Keypair::generate_ed25519());
+
+    for (slot, (first, second)) in (4..).zip([
+        (block.clone(), block.clone()),
+        (block.clone(), next),
+        (block.clone(), tampered),
+        (block.clone(), bad_signature),
+        (block.clone(), other_signer),
+    ]) {
+        let evidence = Action::ReportDoubleSign { first: Box::new(first), second: Box::new(second) };
+        let result = execute(&mut blockchain, &reporter, evidence, &validator, slot);
+        assert!(matches!(result, ActionResult::Error { .. }), "unexpected result: {:?}", result);
+    }
+
+    // Two different headers both sealed by someone outside the validator set.
+    let outsider = Keypair::generate_ed25519();
+    let first = double_sign(&block, &outsider);
+    let second = double_sign(&first, &outsider);
+    let evidence = Action::ReportDoubleSign { first: Box::new(first), second: Box::new(second) };
+    match execute(&mut blockchain, &reporter, evidence, &validator, 9) {
+        ActionResult::Error { error: GameError::InvalidEvidence { reason } } => assert!(reason.contains("not a validator"), "unexpected reason: {}", reason),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert_eq!(stake_of(&mut blockchain, &validator.id), 50 * DTOSHI);
+}
+
+#[test]
+fn validators_missing_too_many_slots_are_jailed() {
+    let (online, offline) = (Account::new(), Account::new());
+    let mut producer = testnet(&[&online, &offline]);
+    let mut follower = testnet(&[&online, &offline]);
+
+    // Round robin: the online validator gets even slots, the offline one misses every odd slot.
+    let mut slot = 0;
+    for _ in 0..JAIL_MISSED_SLOTS {
+        slot += 2;
+        let block = producer.propose_block(&online.keypair, slot_time(slot)).unwrap();
+        follower.import_block(block).unwrap();
+    }
+    assert_eq!(follower.state_root(), producer.state_root());
+
+    // Once jailed, every slot belongs to the validator still online.
+    let block = producer.propose_block(&online.keypair, slot_time(slot + 1)).unwrap();
+    follower.import_block(block).unwrap();
+    assert!(producer.propose_block(&offline.keypair, slot_time(slot + 2)).is_none());
+
+    // It has to sit out the jail period before it may unjail.
+    producer.allocate_mtoshi(&offline.id, DTOSHI).unwrap();
+    let unjail = Action::Unjail { validator: offline.id.clone() };
+    let release_height = JAIL_MISSED_SLOTS + JAIL_PERIOD_BLOCKS;
+    slot += 2;
+    let result = execute(&mut producer, &offline, unjail.clone(), &online, slot);
+    assert!(matches!(result, ActionResult::Error { error: GameError::NotAllowed { .. } }), "unexpected result: {:?}", result);
+    while producer.chain().height() + 1 < release_height {
+        slot += 1;
+        producer.propose_block(&online.keypair, slot_time(slot)).unwrap();
+    }
+
+    slot += 1;
+    let result = execute(&mut producer, &offline, unjail.clone(), &online, slot);
+    assert!(matches!(result, ActionResult::ValidatorUnjailed { .. }), "unexpected result: {:?}", result);
+    assert_eq!(producer.chain().height(), release_height);
+    assert!(matches!(execute(&mut producer, &offline, unjail, &online, slot + 2), ActionResult::Error { .. }));
+}
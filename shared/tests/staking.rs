@@ -0,0 +1,164 @@
+mod common;
+
+use common::{mine, receipt, Account};
+use shared::auth::SignedAction;
+use shared::blockchain::{Action, ActionResult, Blockchain};
+use shared::chain::GENESIS_TIMESTAMP;
+use shared::consensus::{Consensus, ValidatorSet};
+use shared::runtime::{BLOCK_REWARD, MINER_REWARD_PERCENT};
+use shared::staking::UNBONDING_BLOCKS;
+
+const DTOSHI: u64 = 1_000_000_000;
+const SLOT_SECS: u64 = 5;
+
+/// A single-validator chain, so every slot is the validator's turn.
+struct Testnet {
+    blockchain: Blockchain,
+    validator: Account,
+    slot: u64,
+}
+
+impl Testnet {
+    fn new(accounts: &[&Account]) -> Self {
+        let validator = Account::new();
+        let consensus = Consensus::ProofOfAuthority(ValidatorSet::new(vec![validator.id.clone()], SLOT_SECS));
+        let mut blockchain = Blockchain::with_consensus(consensus);
+        for account in accounts {
+            blockchain.allocate_mtoshi(&account.id, 100 * DTOSHI).unwrap();
+        }
+        Testnet { blockchain, validator, slot: 0 }
+    }
+
+    fn produce(&mut self) {
+        self.slot += 1;
+        let now = GENESIS_TIMESTAMP + self.slot * SLOT_SECS;
+        self.blockchain.propose_block(&self.validator.keypair, now).expect("validator's turn");
+    }
+
+    /// Queues `action`, produces the block including it and checks that it succeeded.
+    fn execute(&mut self, account: &Account, action: Action) -> ActionResult {
+        let hash = account.submit(&mut self.blockchain, action);
+        self.produce();
+        let result = receipt(&mut self.blockchain, &hash);
+        assert!(!matches!(result, ActionResult::Error { .. }), "action failed: {:?}", result);
+        result
+    }
+}
+
+fn stake_info(blockchain: &mut Blockchain, player: &str) -> (u64, Vec<(u64, u64)>, u64) {
+    match blockchain.process_action(Action::GetStake { player: player.to_string() }) {
+        ActionResult::StakeInfo { stake, unbonding, rewards, .. } => (stake, unbonding, rewards),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+fn balances(blockchain: &mut Blockchain, player: &str) -> (u64, u64) {
+    match blockchain.process_action(Action::ShowLeaderboard) {
+        ActionResult::Leaderboard { players } => players
+            .into_iter()
+            .find(|(id, ..)| id == player)
+            .map(|(_, _, _, solutio, mtoshi)| (solutio, mtoshi))
+            .expect("player exists"),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn block_rewards_go_to_stakers_pro_rata() {
+    let (alice, bob) = (Account::new(), Account::new());
+    let mut testnet = Testnet::new(&[&alice, &bob]);
+    alice.submit(&mut testnet.blockchain, Action::Stake { player: alice.id.clone(), amount: 40 * DTOSHI });
+    testnet.execute(&bob, Action::Stake { player: bob.id.clone(), amount: 10 * DTOSHI });
+    assert_eq!(stake_info(&mut testnet.blockchain, &alice.id).0, 40 * DTOSHI);
+
+    // Both stakes are in the first block, before its reward is paid out.
+    for _ in 0..3 {
+        testnet.produce();
+    }
+    let (_, _, alice_rewards) = stake_info(&mut testnet.blockchain, &alice.id);
+    let (_, _, bob_rewards) = stake_info(&mut testnet.blockchain, &bob.id);
+    assert_eq!(alice_rewards + bob_rewards, 4 * BLOCK_REWARD.units());
+    assert_eq!(alice_rewards, 4 * bob_rewards);
+
+    let (solutio_before, _) = balances(&mut testnet.blockchain, &alice.id);
+    let claimed = match testnet.execute(&alice, Action::ClaimRewards { player: alice.id.clone() }) {
+        ActionResult::RewardsClaimed { amount, .. } => amount,
+        other => panic!("unexpected result: {:?}", other),
+    };
+    assert_eq!(claimed, alice_rewards);
+    assert_eq!(balances(&mut testnet.blockchain, &alice.id).0, solutio_before + alice_rewards);
+    // The block with the claim pays out its own reward afterwards.
+    assert_eq!(stake_info(&mut testnet.blockchain, &alice.id).2, alice_rewards / 4);
+}
+
+#[test]
+fn proof_of_work_miners_keep_their_share_once_anyone_stakes() {
+    let (alice, miner) = (Account::new(), Account::new());
+    let mut blockchain = Blockchain::new();
+    for account in [&alice, &miner] {
+        blockchain.allocate_mtoshi(&account.id, 100 * DTOSHI).unwrap();
+    }
+    let stake = Action::Stake { player: alice.id.clone(), amount: 40 * DTOSHI };
+    assert!(matches!(alice.execute(&mut blockchain, &miner, stake), ActionResult::Staked { .. }));
+
+    let (solutio_before, _) = balances(&mut blockchain, &miner.id);
+    let (_, _, rewards_before) = stake_info(&mut blockchain, &alice.id);
+    mine(&mut blockchain, &miner);
+    let kept = BLOCK_REWARD.percent(MINER_REWARD_PERCENT).unwrap().units();
+    assert!(kept > 0);
+    assert_eq!(balances(&mut blockchain, &miner.id).0, solutio_before + kept);
+    assert_eq!(stake_info(&mut blockchain, &alice.id).2, rewards_before + BLOCK_REWARD.units() - kept);
+}
+
+#[test]
+fn unstaked_mtoshi_is_released_after_the_unbonding_period() {
+    let alice = Account::new();
+    let mut testnet = Testnet::new(&[&alice]);
+    testnet.execute(&alice, Action::Stake { player: alice.id.clone(), amount: 40 * DTOSHI });
+    let (_, staked_balance) = balances(&mut testnet.blockchain, &alice.id);
+
+    let release_height = match testnet.execute(&alice, Action::Unstake { player: alice.id.clone(), amount: 40 * DTOSHI }) {
+        ActionResult::Unstaked { release_height, .. } => release_height,
+        other => panic!("unexpected result: {:?}", other),
+    };
+    assert_eq!(release_height, testnet.blockchain.chain().height() + UNBONDING_BLOCKS);
+    let (_, unbonding_balance) = balances(&mut testnet.blockchain, &alice.id);
+    // Alice was the only staker for the reward of the block with her stake.
+    assert_eq!(stake_info(&mut testnet.blockchain, &alice.id), (0, vec![(release_height, 40 * DTOSHI)], BLOCK_REWARD.units()));
+
+    while testnet.blockchain.chain().height() < release_height - 1 {
+        testnet.produce();
+    }
+    assert_eq!(balances(&mut testnet.blockchain, &alice.id).1, unbonding_balance);
+    testnet.produce();
+    assert_eq!(balances(&mut testnet.blockchain, &alice.id).1, unbonding_balance + 40 * DTOSHI);
+    assert!(unbonding_balance < staked_balance);
+    assert_eq!(stake_info(&mut testnet.blockchain, &alice.id).1, vec![]);
+}
+
+#[test]
+fn unstaking_more_than_staked_fails() {
+    let alice = Account::new();
+    let mut testnet = Testnet::new(&[&alice]);
+    testnet.execute(&alice, Action::Stake { player: alice.id.clone(), amount: DTOSHI });
+    let unstake = SignedAction::sign(&alice.keypair, Action::Unstake { player: alice.id.clone(), amount: 2 * DTOSHI }, 1);
+    let hash = testnet.blockchain.submit_action(unstake).unwrap();
+    testnet.produce();
+    assert!(matches!(receipt(&mut testnet.blockchain, &hash), ActionResult::Error { .. }));
+    assert_eq!(stake_info(&mut testnet.blockchain, &alice.id).0, DTOSHI);
+}
+
+#[test]
+fn slots_are_assigned_in_proportion_to_stake() {
+    let validators = ValidatorSet::new(vec!["heavy".to_string(), "light".to_string(), "unstaked".to_string()], SLOT_SECS);
+    let stake_of = |id: &str| match id {
+        "heavy" => Some(300),
+        "light" => Some(100),
+        _ => Some(0),
+    };
+    let heavy = (0..4000).filter(|slot| validators.scheduled_validator(*slot, stake_of) == Some("heavy")).count();
+    let unstaked = (0..4000).filter(|slot| validators.scheduled_validator(*slot, stake_of) == Some("unstaked")).count();
+    assert!((2800..3200).contains(&heavy), "heavy validator got {} of 4000 slots", heavy);
+    assert_eq!(unstaked, 0);
+    assert_eq!(validators.scheduled_validator(7, |_| Some(0)), validators.validator_for(7));
+}
@@ -0,0 +1,161 @@
+use libp2p::identity::Keypair;
+use shared::auth::{account_id, SignedAction};
+use shared::blockchain::{Action, ActionResult, Blockchain, FAUCET_AMOUNT};
+use shared::error::GameError;
+use shared::runtime::catena_node::Block;
+use shared::runtime::MAX_NONCE_ATTEMPTS;
+use shared::state::{verify_account_proof, AccountProof};
+
+const DTOSHI: u64 = 1_000_000_000;
+
+fn two_nodes(accounts: &[&str]) -> (Blockchain, Blockchain) {
+    let node = || {
+        let mut blockchain = Blockchain::new();
+        for id in accounts {
+            blockchain.allocate_mtoshi(id, 100 * DTOSHI).unwrap();
+        }
+        blockchain
+    };
+    (node(), node())
+}
+
+fn mine(blockchain: &mut Blockchain, miner: &Keypair) -> Block {
+    let miner_id = account_id(&miner.public());
+    let nonce = blockchain.account_nonce(&miner_id);
+    let mine = SignedAction::sign(miner, Action::MineBlock { miner: miner_id.clone() }, nonce);
+    match blockchain.process_signed_action(mine).unwrap() {
+        ActionResult::BlockMined { block: Some(block), .. } => block,
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+fn account_proof(blockchain: &mut Blockchain, player: &str, height: Option<u64>) -> AccountProof {
+    match blockchain.process_action(Action::GetAccountProof { player: player.to_string(), height }) {
+        ActionResult::AccountProof { proof } => proof,
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+fn state_root_at(blockchain: &mut Blockchain, height: u64) -> String {
+    match blockchain.process_action(Action::GetStateRoot { height }) {
+        ActionResult::StateRoot { state_root, .. } => state_root,
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn imported_blocks_reproduce_the_state_root() {
+    let miner = Keypair::generate_ed25519();
+    let player = Keypair::generate_ed25519();
+    let (miner_id, player_id) = (account_id(&miner.public()), account_id(&player.public()));
+    let (mut producer, mut follower) = two_nodes(&[&miner_id, &player_id]);
+    assert_eq!(producer.state_root(), follower.state_root());
+
+    let mint = SignedAction::sign(&player, Action::MintNft {
+        player: player_id.clone(),
+        name: "Gem".to_string(),
+        value: 100,
+    }, 0);
+    producer.submit_action(mint).unwrap();
+    let first = mine(&mut producer, &miner);
+    let battle = SignedAction::sign(&player, Action::Battle { player1: player_id.clone(), player2: miner_id.clone() }, 1).cosign(&miner);
+    producer.submit_action(battle).unwrap();
+    let second = mine(&mut producer, &miner);
+    assert_eq!(first.actions.len(), 2);
+    assert_eq!(first.state_root, state_root_at(&mut producer, 1));
+
+    follower.import_block(first).unwrap();
+    follower.import_block(second).unwrap();
+    assert_eq!(follower.chain().height(), 2);
+    assert_eq!(state_root_at(&mut follower, 2), state_root_at(&mut producer, 2));
+    assert_eq!(follower.state_root(), producer.state_root());
+}
+
+#[test]
+fn blocks_with_a_wrong_state_root_are_refused() {
+    let miner = Keypair::generate_ed25519();
+    let miner_id = account_id(&miner.public());
+    let (mut producer, mut follower) = two_nodes(&[&miner_id]);
+    let before = follower.state_root();
+
+    let mut block = mine(&mut producer, &miner);
+    block.state_root = before.clone();
+    assert!(block.mine(MAX_NONCE_ATTEMPTS));
+
+    let error = follower.import_block(block).unwrap_err();
+    assert!(error.contains("state root"), "unexpected error: {}", error);
+    assert_eq!(follower.chain().height(), 0);
+    assert_eq!(follower.state_root(), before);
+    assert_eq!(follower.account_nonce(&miner_id), 0);
+}
+
+#[test]
+fn account_proofs_verify_against_the_block_header() {
+    let miner = Keypair::generate_ed25519();
+    let player = Keypair::generate_ed25519();
+    let (miner_id, player_id) = (account_id(&miner.public()), account_id(&player.public()));
+    let (mut blockchain, _) = two_nodes(&[&miner_id, &player_id]);
+
+    let request = SignedAction::sign(&player, Action::RequestTokens {
+        player: player_id.clone(),
+        token: "Solutio".to_string(),
+    }, 0);
+    blockchain.submit_action(request).unwrap();
+    let header = mine(&mut blockchain, &miner);
+
+    // Actions still waiting for a block do not change what is proven.
+    let post = SignedAction::sign(&player, Action::PostContent {
+        player: player_id.clone(),
+        post_id: "post1".to_string(),
+        caption: "later".to_string(),
+    }, 1);
+    blockchain.process_signed_action(post).unwrap();
+
+    let mut proof = account_proof(&mut blockchain, &player_id, None);
+    assert_eq!(proof.height, header.index);
+    assert_eq!(proof.balances.value["MTOSHI"], 100 * DTOSHI);
+    assert_eq!(proof.balances.value["Solutio"], FAUCET_AMOUNT.units());
+    assert!(verify_account_proof(&header.state_root, &player_id, &proof));
+    assert!(!verify_account_proof(&header.state_root, &miner_id, &proof));
+
+    // Balances cannot be left out, taken from another account or altered.
+    let mut stripped = serde_json::to_value(&proof).unwrap();
+    stripped.as_object_mut().unwrap().remove("balances");
+    assert!(serde_json::from_value::<AccountProof>(stripped).is_err());
+    let mut swapped = proof.clone();
+    swapped.balances = account_proof(&mut blockchain, &miner_id, None).balances;
+    assert!(!verify_account_proof(&header.state_root, &player_id, &swapped));
+    swapped.balances.key = proof.balances.key.clone();
+    assert!(!verify_account_proof(&header.state_root, &player_id, &swapped));
+    proof.balances.value.insert("MTOSHI".to_string(), 100 * DTOSHI + 1);
+    assert!(!verify_account_proof(&header.state_root, &player_id, &proof));
+}
+
+#[test]
+fn account_proofs_can_target_an_earlier_block() {
+    let miner = Keypair::generate_ed25519();
+    let player = Keypair::generate_ed25519();
+    let (miner_id, player_id) = (account_id(&miner.public()), account_id(&player.public()));
+    let (mut blockchain, _) = two_nodes(&[&miner_id, &player_id]);
+
+    let first = mine(&mut blockchain, &miner);
+    let post = SignedAction::sign(&player, Action::PostContent {
+        player: player_id.clone(),
+        post_id: "post1".to_string(),
+        caption: "hello".to_string(),
+    }, 0);
+    blockchain.submit_action(post).unwrap();
+    let second = mine(&mut blockchain, &miner);
+
+    let earlier = account_proof(&mut blockchain, &player_id, Some(first.index));
+    assert_eq!((earlier.height, earlier.balances.value["MTOSHI"]), (first.index, 100 * DTOSHI));
+    assert!(verify_account_proof(&first.state_root, &player_id, &earlier));
+    assert!(!verify_account_proof(&second.state_root, &player_id, &earlier));
+
+    let latest = account_proof(&mut blockchain, &player_id, Some(second.index));
+    assert!(latest.balances.value["MTOSHI"] < 100 * DTOSHI);
+    assert!(verify_account_proof(&second.state_root, &player_id, &latest));
+
+    let future = Action::GetAccountProof { player: player_id.clone(), height: Some(second.index + 1) };
+    assert!(matches!(blockchain.process_action(future), ActionResult::Error { error: GameError::NotFound { .. } }));
+}
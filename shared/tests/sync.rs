@@ -0,0 +1,191 @@
+use libp2p::gossipsub;
+use libp2p::identity::Keypair;
+use libp2p::multiaddr::Protocol;
+use libp2p::request_response::{Event, Message};
+use libp2p::swarm::SwarmEvent;
+use shared::auth::{account_id, SignedAction};
+use shared::blockchain::{Action, ActionResult, Blockchain};
+use shared::chain::{Chain, GENESIS_TIMESTAMP, MAX_REORG_DEPTH};
+use shared::consensus::{Consensus, ValidatorSet};
+use shared::finality::FinalityGadget;
+use shared::network::{actions_topic, blocks_topic, setup_network, CustomEvent, Network, NetworkConfig};
+use shared::sync::{verify_headers, SyncRequest, SyncResponse, MAX_BLOCKS};
+use std::collections::HashSet;
+use std::time::Duration;
+
+const DTOSHI: u64 = 1_000_000_000;
+const SLOT_SECS: u64 = 5;
+
+fn config(dial_addrs: Vec<String>) -> NetworkConfig {
+    NetworkConfig { listen_addr: "/ip4/127.0.0.1/tcp/0".to_string(), dial_addrs, key_file: None, mdns: false }
+}
+
+fn mine(blockchain: &mut Blockchain, miner: &Keypair) {
+    let id = account_id(&miner.public());
+    let mine = SignedAction::sign(miner, Action::MineBlock { miner: id.clone() }, blockchain.account_nonce(&id));
+    assert!(matches!(blockchain.process_signed_action(mine).unwrap(), ActionResult::BlockMined { block: Some(_), .. }));
+}
+
+/// A fresh node whose miner can pay block fees; every node starts from this state.
+fn node(miner: &Keypair) -> Blockchain {
+    let mut blockchain = Blockchain::new();
+    blockchain.allocate_mtoshi(&account_id(&miner.public()), 100 * DTOSHI).unwrap();
+    blockchain
+}
+
+fn chain_of(miner: &Keypair, blocks: u64) -> Blockchain {
+    let mut blockchain = node(miner);
+    for _ in 0..blocks {
+        mine(&mut blockchain, miner);
+    }
+    blockchain
+}
+
+/// An authority chain of `blocks` blocks from a single validator. Chains
+/// built with different `offset`s share only the genesis block.
+fn authority_chain(validator: &Keypair, blocks: u64, offset: u64) -> Blockchain {
+    let validators = ValidatorSet::new(vec![account_id(&validator.public())], SLOT_SECS);
+    let mut blockchain = Blockchain::with_consensus(Consensus::ProofOfAuthority(validators));
+    for slot in 1..=blocks {
+        blockchain.propose_block(validator, GENESIS_TIMESTAMP + slot * SLOT_SECS + offset).expect("validator's turn");
+    }
+    blockchain
+}
+
+/// Serves sync requests from `blockchain` for as long as the test runs. With
+/// a `miner`, it also mines a block and gossips it, along with a new action,
+/// once a peer has subscribed to blocks and actions.
+async fn serve(mut network: Network, mut blockchain: Blockchain, mut miner: Option<Keypair>) {
+    network.join_gossip();
+    let mut subscribed = HashSet::new();
+    loop {
+        match network.next_event().await {
+            SwarmEvent::Behaviour(CustomEvent::Sync(Event::Message { peer, message: Message::Request { request, channel, .. } })) => {
+                network.answer_sync_request(peer, request, channel, blockchain.chain());
+            }
+            SwarmEvent::Behaviour(CustomEvent::Gossipsub(gossipsub::Event::Subscribed { topic, .. })) => {
+                subscribed.insert(topic);
+                if ![blocks_topic(), actions_topic()].iter().all(|topic| subscribed.contains(&topic.hash())) {
+                    continue;
+                }
+                if let Some(miner) = miner.take() {
+                    mine(&mut blockchain, &miner);
+                    network.publish_block(blockchain.chain().head()).unwrap();
+                    let id = account_id(&miner.public());
+                    let action = Action::RecordActivity { player: id.clone(), activity_type: 1 };
+                    network.publish_action(&SignedAction::sign(&miner, action, blockchain.account_nonce(&id))).unwrap();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+async fn listen_addr(network: &mut Network) -> String {
+    let peer_id = *network.swarm.local_peer_id();
+    loop {
+        if let SwarmEvent::NewListenAddr { address, .. } = network.next_event().await {
+            return address.with(Protocol::P2p(peer_id)).to_string();
+        }
+    }
+}
+
+#[test]
+fn requests_are_answered_from_the_main_chain() {
+    let blockchain = chain_of(&Keypair::generate_ed25519(), 3);
+    let chain: &Chain = blockchain.chain();
+
+    match (SyncRequest::GetHeaders { from: 1, count: 10 }).answer(chain) {
+        SyncResponse::Headers(headers) => {
+            assert_eq!(headers.iter().map(|header| header.index).collect::<Vec<_>>(), vec![1, 2, 3]);
+            assert!(headers.iter().all(|header| header.actions.is_empty()));
+            verify_headers(&headers).unwrap();
+        }
+        other => panic!("unexpected response: {:?}", other),
+    }
+
+    let mut hashes: Vec<String> = chain.blocks().iter().map(|block| block.hash.clone()).collect();
+    hashes.insert(1, "unknown".to_string());
+    match (SyncRequest::GetBlocks { hashes }).answer(chain) {
+        SyncResponse::Blocks(blocks) => {
+            let expected: Vec<&String> = chain.blocks().iter().map(|block| &block.hash).collect();
+            assert_eq!(blocks.iter().map(|block| &block.hash).collect::<Vec<_>>(), expected);
+            assert!(blocks[1..].iter().all(|block| block.verify().is_ok()));
+        }
+        other => panic!("unexpected response: {:?}", other),
+    }
+    let hashes = vec![chain.head().hash.clone(); MAX_BLOCKS + 1];
+    assert!(matches!((SyncRequest::GetBlocks { hashes }).answer(chain), SyncResponse::Blocks(blocks) if blocks.len() == MAX_BLOCKS));
+
+    match SyncRequest::GetStatus.answer(chain) {
+        SyncResponse::Status(status) => {
+            assert_eq!((status.height, &status.hash, status.work), (3, &chain.head().hash, chain.work()));
+        }
+        other => panic!("unexpected response: {:?}", other),
+    }
+}
+
+#[test]
+fn tampered_headers_are_refused() {
+    let blockchain = chain_of(&Keypair::generate_ed25519(), 2);
+    let mut headers: Vec<_> = blockchain.chain().blocks().iter().map(|block| block.without_body()).collect();
+    verify_headers(&headers).unwrap();
+    headers.swap(1, 2);
+    assert!(verify_headers(&headers).is_err());
+    headers.swap(1, 2);
+    headers[2].state_root = "00".repeat(32);
+    assert!(verify_headers(&headers).is_err());
+}
+
+#[tokio::test]
+async fn a_new_node_catches_up_before_joining_gossip() {
+    let miner = Keypair::generate_ed25519();
+    let source = chain_of(&miner, 5);
+    let head = source.chain().head().hash.clone();
+    let mut serving = setup_network(config(vec![])).await.unwrap();
+    let addr = listen_addr(&mut serving).await;
+    tokio::spawn(serve(serving, source, Some(miner.clone())));
+
+    let mut blockchain = node(&miner);
+    let mut node = setup_network(config(vec![addr])).await.unwrap();
+    let imported = tokio::time::timeout(Duration::from_secs(20), node.catch_up(&mut blockchain)).await.expect("sync finishes").unwrap();
+    assert_eq!(imported, 5);
+    assert!(blockchain.chain().contains(&head));
+
+    // Once subscribed, the node follows new blocks and actions over gossip.
+    let mut gadget = FinalityGadget::new(Vec::new(), None);
+    let gossip = async {
+        while blockchain.chain().height() < 6 || blockchain.mempool().is_empty() {
+            let event = node.next_event().await;
+            node.handle_gossip(&event, &mut blockchain, &mut gadget);
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(20), gossip).await.expect("gossip arrives");
+    assert_eq!(blockchain.chain().get_by_height(5).unwrap().hash, head);
+
+    // Nothing new to fetch the second time round.
+    assert_eq!(node.catch_up(&mut blockchain).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn a_peer_forking_off_too_deep_is_refused() {
+    let validator = Keypair::generate_ed25519();
+    let mut serving = setup_network(config(vec![])).await.unwrap();
+    let addr = listen_addr(&mut serving).await;
+    tokio::spawn(serve(serving, authority_chain(&validator, MAX_REORG_DEPTH + 3, 1), None));
+
+    let mut blockchain = authority_chain(&validator, MAX_REORG_DEPTH + 1, 0);
+    let head = blockchain.chain().head().hash.clone();
+    let mut node = setup_network(config(vec![addr])).await.unwrap();
+    let result = tokio::time::timeout(Duration::from_secs(20), node.catch_up(&mut blockchain)).await.expect("sync finishes");
+    assert!(result.unwrap_err().contains("forks off more than"));
+    assert_eq!(blockchain.chain().head().hash, head);
+}
+
+#[tokio::test]
+async fn a_lone_node_starts_from_its_own_chain() {
+    let mut blockchain = chain_of(&Keypair::generate_ed25519(), 1);
+    let mut node = setup_network(config(vec![])).await.unwrap();
+    assert_eq!(node.catch_up(&mut blockchain).await.unwrap(), 0);
+    assert_eq!(blockchain.chain().height(), 1);
+}